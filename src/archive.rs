@@ -0,0 +1,270 @@
+use crate::date::Date;
+use flate2::read::GzDecoder;
+use std::{
+    fs::{self, File},
+    io::{self, Read, Seek, Write},
+    path::{Path, PathBuf},
+};
+use tar::{Archive, Builder, EntryType, Header};
+use zip::{write::FileOptions, CompressionMethod, DateTime, ZipWriter};
+
+// same fixed timestamp npm uses for its tarballs (1985-10-26T08:15:00Z)
+pub const DEFAULT_MTIME: u64 = 499162500;
+
+/// Resolves the mtime every archive entry gets.
+///
+/// An explicit value wins, then `SOURCE_DATE_EPOCH`, then [`DEFAULT_MTIME`].
+pub fn resolve_mtime(mtime: Option<u64>) -> u64 {
+    if let Some(mtime) = mtime {
+        return mtime;
+    }
+
+    match std::env::var("SOURCE_DATE_EPOCH") {
+        Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+            eprintln!("Ignoring invalid SOURCE_DATE_EPOCH `{}`", value);
+            DEFAULT_MTIME
+        }),
+        Err(_) => DEFAULT_MTIME,
+    }
+}
+
+/// Total size in bytes of every file below `root`.
+pub fn dir_size(root: &Path) -> io::Result<u64> {
+    let mut entries = Vec::new();
+    collect_entries(root, &mut entries)?;
+
+    let mut size = 0;
+    for entry in entries {
+        let metadata = fs::metadata(root.join(entry))?;
+        if metadata.is_file() {
+            size += metadata.len();
+        }
+    }
+
+    Ok(size)
+}
+
+/// Appends `root` and everything below it under `prefix`, sorted by path,
+/// with normalized mtimes, owners and modes.
+///
+/// `on_read` is called with the number of bytes read from each file chunk.
+pub fn append_dir_sorted<W: Write>(
+    tar: &mut Builder<W>,
+    prefix: &str,
+    root: &Path,
+    mtime: u64,
+    on_read: &mut dyn FnMut(u64),
+) -> io::Result<()> {
+    let mut entries = Vec::new();
+    collect_entries(root, &mut entries)?;
+    entries.sort();
+
+    let mut header = new_header(EntryType::Directory, 0, mtime);
+    tar.append_data(&mut header, format!("{}/", prefix), io::empty())?;
+
+    for entry in entries {
+        let path = root.join(&entry);
+        let name = entry_name(prefix, &entry);
+
+        if path.is_dir() {
+            let mut header = new_header(EntryType::Directory, 0, mtime);
+            tar.append_data(&mut header, format!("{}/", name), io::empty())?;
+        } else {
+            let file = File::open(&path)?;
+            let size = file.metadata()?.len();
+            let reader = Progress {
+                inner: file,
+                on_read: &mut *on_read,
+            };
+            append_data(tar, &name, size, reader, mtime)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Appends a single file entry with a normalized header.
+pub fn append_data<W: Write>(
+    tar: &mut Builder<W>,
+    name: &str,
+    size: u64,
+    data: impl Read,
+    mtime: u64,
+) -> io::Result<()> {
+    let mut header = new_header(EntryType::Regular, size, mtime);
+    tar.append_data(&mut header, name, data)
+}
+
+/// Zip counterpart of [`append_dir_sorted`].
+pub fn zip_dir_sorted<W: Write + Seek>(
+    zip: &mut ZipWriter<W>,
+    prefix: &str,
+    root: &Path,
+    options: FileOptions,
+    on_read: &mut dyn FnMut(u64),
+) -> io::Result<()> {
+    let mut entries = Vec::new();
+    collect_entries(root, &mut entries)?;
+    entries.sort();
+
+    zip.add_directory(prefix, options.unix_permissions(0o755))?;
+
+    for entry in entries {
+        let path = root.join(&entry);
+        let name = entry_name(prefix, &entry);
+
+        if path.is_dir() {
+            zip.add_directory(name, options.unix_permissions(0o755))?;
+        } else {
+            let mut reader = Progress {
+                inner: File::open(&path)?,
+                on_read: &mut *on_read,
+            };
+            zip_data(zip, &name, &mut reader, options)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Zip counterpart of [`append_data`].
+pub fn zip_data<W: Write + Seek>(
+    zip: &mut ZipWriter<W>,
+    name: &str,
+    mut data: impl Read,
+    options: FileOptions,
+) -> io::Result<()> {
+    zip.start_file(name, options.unix_permissions(0o644))?;
+    io::copy(&mut data, zip)?;
+    Ok(())
+}
+
+/// Zip entry options matching the normalized tar headers.
+pub fn zip_options(level: u32, mtime: u64) -> FileOptions {
+    let method = match level {
+        0 => CompressionMethod::Stored,
+        _ => CompressionMethod::Deflated,
+    };
+
+    FileOptions::default()
+        .compression_method(method)
+        .compression_level(Some(level as i32))
+        .last_modified_time(zip_time(mtime))
+}
+
+// zip stores local MS-DOS times, which can't go below 1980
+fn zip_time(mtime: u64) -> DateTime {
+    let date = Date::from_unix(mtime);
+
+    DateTime::from_date_and_time(
+        date.year.clamp(0, u16::MAX as i64) as u16,
+        date.month as u8,
+        date.day as u8,
+        date.hour as u8,
+        date.minute as u8,
+        date.second as u8,
+    )
+    .unwrap_or_default()
+}
+
+pub fn open_tgz(path: &Path) -> io::Result<Archive<GzDecoder<File>>> {
+    Ok(Archive::new(GzDecoder::new(File::open(path)?)))
+}
+
+/// Turns an entry path like `package/ProjectData~/Assets` into `ProjectData~/Assets`.
+///
+/// The root folder is usually `package`, but isn't guaranteed to be.
+pub fn package_path(entry: &Path) -> String {
+    entry
+        .components()
+        .filter(|component| matches!(component, std::path::Component::Normal(_)))
+        .skip(1)
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Extracts every file of a `.tgz` below `dest`.
+///
+/// `map` receives each file's [`package_path`] and returns where it goes
+/// relative to `dest`, or `None` to skip it.
+pub fn extract_tgz(
+    path: &Path,
+    dest: &Path,
+    map: impl Fn(&str) -> Option<String>,
+) -> io::Result<()> {
+    let mut archive = open_tgz(path)?;
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let target = match map(&package_path(&entry.path()?)) {
+            Some(target) => dest.join(target),
+            None => continue,
+        };
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        entry.unpack(&target)?;
+    }
+
+    Ok(())
+}
+
+struct Progress<'a, R> {
+    inner: R,
+    on_read: &'a mut dyn FnMut(u64),
+}
+
+impl<R: Read> Read for Progress<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        (self.on_read)(read as u64);
+        Ok(read)
+    }
+}
+
+fn new_header(entry_type: EntryType, size: u64, mtime: u64) -> Header {
+    let mut header = Header::new_gnu();
+    header.set_entry_type(entry_type);
+    header.set_size(size);
+    header.set_mtime(mtime);
+    header.set_uid(0);
+    header.set_gid(0);
+    header.set_mode(match entry_type {
+        EntryType::Directory => 0o755,
+        _ => 0o644,
+    });
+    header
+}
+
+// tar entries always use forward slashes, regardless of the host
+fn entry_name(prefix: &str, relative: &Path) -> String {
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .fold(prefix.to_string(), |acc, part| format!("{}/{}", acc, part))
+}
+
+fn collect_entries(root: &Path, entries: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut stack = vec![PathBuf::new()];
+
+    while let Some(relative) = stack.pop() {
+        for entry in fs::read_dir(root.join(&relative))? {
+            let entry = entry?;
+            let path = relative.join(entry.file_name());
+
+            if entry.file_type()?.is_dir() {
+                stack.push(path.clone());
+            }
+
+            entries.push(path);
+        }
+    }
+
+    Ok(())
+}
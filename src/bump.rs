@@ -0,0 +1,219 @@
+use crate::documents;
+use semver::{Prerelease, Version};
+
+#[derive(Clone, Copy, Debug, clap::ValueEnum)]
+pub enum Part {
+    Major,
+    Minor,
+    Patch,
+    Prerelease,
+}
+
+/// Bumps a version the same way `npm version` does.
+pub fn bump(version: &Version, part: Part, preid: Option<&str>) -> Result<Version, String> {
+    let mut next = version.clone();
+    next.build = semver::BuildMetadata::EMPTY;
+
+    match part {
+        Part::Major => {
+            // 2.0.0-beta.1 is released as 2.0.0
+            if version.pre.is_empty() || version.minor != 0 || version.patch != 0 {
+                next.major += 1;
+            }
+            next.minor = 0;
+            next.patch = 0;
+            next.pre = Prerelease::EMPTY;
+        }
+        Part::Minor => {
+            if version.pre.is_empty() || version.patch != 0 {
+                next.minor += 1;
+            }
+            next.patch = 0;
+            next.pre = Prerelease::EMPTY;
+        }
+        Part::Patch => {
+            if version.pre.is_empty() {
+                next.patch += 1;
+            }
+            next.pre = Prerelease::EMPTY;
+        }
+        Part::Prerelease => {
+            let pre = if version.pre.is_empty() {
+                next.patch += 1;
+                match preid {
+                    Some(preid) => format!("{}.0", preid),
+                    None => "0".to_string(),
+                }
+            } else {
+                next_prerelease(version.pre.as_str(), preid)
+            };
+
+            next.pre = Prerelease::new(&pre).map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(next)
+}
+
+// beta.1 -> beta.2, beta -> beta.0, and a new preid starts over at <preid>.0
+fn next_prerelease(pre: &str, preid: Option<&str>) -> String {
+    if let Some(preid) = preid {
+        if pre.split('.').next() != Some(preid) {
+            return format!("{}.0", preid);
+        }
+    }
+
+    let mut parts: Vec<String> = pre.split('.').map(|part| part.to_string()).collect();
+    match parts.iter().rposition(|part| part.parse::<u64>().is_ok()) {
+        Some(index) => {
+            let number: u64 = parts[index].parse().unwrap();
+            parts[index] = (number + 1).to_string();
+        }
+        None => parts.push("0".to_string()),
+    }
+
+    parts.join(".")
+}
+
+/// Adds a release section to a Keep a Changelog file.
+///
+/// Anything under `## [Unreleased]` moves into the new release, and a fresh
+/// empty `Unreleased` section is left above it.
+pub fn add_changelog_entry(changelog: &str, version: &Version, date: &str) -> String {
+    let heading = format!("## [{}] - {}", version, date);
+
+    if changelog.trim().is_empty() {
+        return format!(
+            "{}\n## [Unreleased]\n\n{}\n",
+            documents::CHANGELOG_HEADER,
+            heading
+        );
+    }
+
+    let mut lines: Vec<String> = changelog.lines().map(|line| line.to_string()).collect();
+
+    match lines
+        .iter()
+        .position(|line| line.trim().eq_ignore_ascii_case("## [Unreleased]"))
+    {
+        Some(index) => {
+            lines[index] = heading;
+            lines.insert(index, String::new());
+            lines.insert(index, "## [Unreleased]".to_string());
+        }
+        None => {
+            // above the newest release, or at the end if there is none yet
+            let index = lines
+                .iter()
+                .position(|line| line.starts_with("## "))
+                .unwrap_or(lines.len());
+            lines.insert(index, String::new());
+            lines.insert(index, heading);
+        }
+    }
+
+    let mut changelog = lines.join("\n");
+    changelog.push('\n');
+    changelog
+}
+
+/// Updates the `Version:` line of a generated README.
+///
+/// The rest of the README is left as it is, so edits made to it are kept.
+pub fn set_readme_version(readme: &str, current: &Version, next: &Version) -> String {
+    let old_line = format!("- Version: {}", current);
+    let mut lines: Vec<String> = readme.lines().map(|line| line.to_string()).collect();
+
+    for line in lines.iter_mut().filter(|line| line.trim_end() == old_line) {
+        *line = format!("- Version: {}", next);
+    }
+
+    let mut readme = lines.join("\n");
+    if !readme.is_empty() {
+        readme.push('\n');
+    }
+    readme
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bumped(version: &str, part: Part, preid: Option<&str>) -> String {
+        bump(&Version::parse(version).unwrap(), part, preid)
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn major() {
+        assert_eq!(bumped("1.2.3", Part::Major, None), "2.0.0");
+        assert_eq!(bumped("2.0.0-beta.1", Part::Major, None), "2.0.0");
+        assert_eq!(bumped("1.2.0-beta.1", Part::Major, None), "2.0.0");
+    }
+
+    #[test]
+    fn minor() {
+        assert_eq!(bumped("1.2.3", Part::Minor, None), "1.3.0");
+        assert_eq!(bumped("1.3.0-rc.0", Part::Minor, None), "1.3.0");
+        assert_eq!(bumped("1.3.1-rc.0", Part::Minor, None), "1.4.0");
+    }
+
+    #[test]
+    fn patch() {
+        assert_eq!(bumped("1.2.3", Part::Patch, None), "1.2.4");
+        assert_eq!(bumped("1.2.4-0", Part::Patch, None), "1.2.4");
+        assert_eq!(bumped("1.2.3+build.5", Part::Patch, None), "1.2.4");
+    }
+
+    #[test]
+    fn prerelease() {
+        assert_eq!(bumped("1.2.3", Part::Prerelease, None), "1.2.4-0");
+        assert_eq!(
+            bumped("1.2.3", Part::Prerelease, Some("beta")),
+            "1.2.4-beta.0"
+        );
+        assert_eq!(
+            bumped("1.0.0-beta.1", Part::Prerelease, None),
+            "1.0.0-beta.2"
+        );
+        assert_eq!(bumped("1.0.0-beta", Part::Prerelease, None), "1.0.0-beta.0");
+        assert_eq!(
+            bumped("1.0.0-beta.1", Part::Prerelease, Some("beta")),
+            "1.0.0-beta.2"
+        );
+    }
+
+    #[test]
+    fn prerelease_switches_preid() {
+        assert_eq!(
+            bumped("1.0.0-alpha.3", Part::Prerelease, Some("beta")),
+            "1.0.0-beta.0"
+        );
+        // `b` is a different identifier, not a prefix of `beta`
+        assert_eq!(
+            bumped("1.0.0-beta.1", Part::Prerelease, Some("b")),
+            "1.0.0-b.0"
+        );
+    }
+
+    #[test]
+    fn readme_version() {
+        let current = Version::parse("1.0.0").unwrap();
+        let next = Version::parse("1.1.0").unwrap();
+
+        assert_eq!(
+            set_readme_version(
+                "# Demo\n\nBuilt on 1.0.0.\n\n- Package: `demo`\n- Version: 1.0.0\n",
+                &current,
+                &next
+            ),
+            "# Demo\n\nBuilt on 1.0.0.\n\n- Package: `demo`\n- Version: 1.1.0\n"
+        );
+        // only the current version is replaced
+        assert_eq!(
+            set_readme_version("- Version: 0.9.0\n", &current, &next),
+            "- Version: 0.9.0\n"
+        );
+    }
+}
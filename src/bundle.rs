@@ -1,28 +1,260 @@
+use crate::{documents::Documents, images::Image};
 use serde_derive::{Deserialize, Serialize};
 use std::{fs, path::Path};
 
-#[derive(Serialize, Deserialize)]
-pub struct Dependencies {}
+/// Prefix of Unity's own templates, and the default for ours
+pub const PACKAGE_PREFIX: &str = "com.unity.template.";
 
-#[derive(Serialize, Deserialize)]
+/// UPM caps package names at the npm limit
+const MAX_NAME_LENGTH: usize = 214;
+
+/// Where the manifest override script lives inside `package/`
+pub const BOOTSTRAP_PATH: &str = "ProjectData~/Assets/___ManifestOverride.cs";
+
+/// A template's package.json.
+///
+/// Fields this tool doesn't know about are kept in `extra`, and fields it knows are only written
+/// back when the file had them, so reading and writing a package.json never drops or adds anything.
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Data {
     pub name: String,
-    #[serde(rename(serialize = "displayName"))]
-    pub display_name: String,
+    #[serde(
+        rename = "displayName",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub display_name: Option<String>,
     pub version: String,
-    pub unity: String,
-    #[serde(rename(serialize = "unityFull"))]
-    pub unity_full: String,
-    pub keywords: Vec<String>,
-    pub category: String,
-    pub description: String,
-    pub dependencies: serde_json::Value,
-    #[serde(rename(serialize = "defaultScene"))]
-    pub default_scene: String,
-    pub repository: String,
-}
-
-pub fn build(project_path: &str, data: Data) {
+    /// Always `template` for packages the Hub lists
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    /// Always `hub` for packages the Hub lists
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unity: Option<String>,
+    #[serde(rename = "unityFull", default, skip_serializing_if = "Option::is_none")]
+    pub unity_full: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keywords: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<Author>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dependencies: Option<serde_json::Value>,
+    #[serde(
+        rename = "defaultScene",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub default_scene: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repository: Option<Repository>,
+    #[serde(
+        rename = "documentationUrl",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub documentation_url: Option<String>,
+    #[serde(
+        rename = "changelogUrl",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub changelog_url: Option<String>,
+    #[serde(
+        rename = "licensesUrl",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub licenses_url: Option<String>,
+    #[serde(
+        rename = "previewImage",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub preview_image: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail: Option<String>,
+    /// Hides the package from the editor's Package Manager window
+    #[serde(
+        rename = "hideInEditor",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub hide_in_editor: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub samples: Option<Vec<Sample>>,
+    /// Set by Unity's CI on their own templates
+    #[serde(rename = "upmCi", default, skip_serializing_if = "Option::is_none")]
+    pub upm_ci: Option<UpmCi>,
+    /// Set by the Package Manager on published packages
+    #[serde(rename = "_upm", default, skip_serializing_if = "Option::is_none")]
+    pub upm: Option<Upm>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Data {
+    /// Reads a package.json.
+    ///
+    /// Known fields that are explicitly `null` are kept in `extra`, so they are written back.
+    pub fn from_value(mut package: serde_json::Value) -> serde_json::Result<Data> {
+        let nulls: Vec<String> = package
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter(|(_, value)| value.is_null())
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        let mut extra = serde_json::Map::new();
+        if let Some(object) = package.as_object_mut() {
+            for key in nulls {
+                object.remove(&key);
+                extra.insert(key, serde_json::Value::Null);
+            }
+        }
+
+        let mut data: Data = serde_json::from_value(package)?;
+        data.extra.extend(extra);
+        Ok(data)
+    }
+
+    /// The package.json to write, where fields set since reading win over `null`s kept in `extra`.
+    pub fn to_value(&self) -> serde_json::Result<serde_json::Value> {
+        let mut data = self.clone();
+        let extra = std::mem::take(&mut data.extra);
+        let mut package = serde_json::to_value(data)?;
+
+        let fields = package.as_object_mut().unwrap();
+        for (key, value) in extra {
+            if !(value.is_null() && fields.contains_key(&key)) {
+                fields.insert(key, value);
+            }
+        }
+
+        Ok(package)
+    }
+
+    pub fn display_name(&self) -> &str {
+        self.display_name.as_deref().unwrap_or(&self.name)
+    }
+
+    pub fn unity_full(&self) -> &str {
+        self.unity_full.as_deref().unwrap_or_default()
+    }
+
+    pub fn default_scene(&self) -> &str {
+        self.default_scene.as_deref().unwrap_or_default()
+    }
+}
+
+/// npm allows both `"author": "name"` and `"author": { "name": "..." }`
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum Author {
+    Name(String),
+    Details {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        email: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        url: Option<String>,
+        #[serde(flatten)]
+        extra: serde_json::Map<String, serde_json::Value>,
+    },
+    /// Anything else is kept as it was
+    Other(serde_json::Value),
+}
+
+/// npm allows both `"repository": "url"` and `"repository": { "url": "..." }`
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum Repository {
+    Url(String),
+    Details {
+        #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+        kind: Option<String>,
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        revision: Option<String>,
+        #[serde(flatten)]
+        extra: serde_json::Map<String, serde_json::Value>,
+    },
+    /// Anything else is kept as it was
+    Other(serde_json::Value),
+}
+
+impl Repository {
+    pub fn url(&self) -> Option<&str> {
+        match self {
+            Repository::Url(url) => Some(url),
+            Repository::Details { url, .. } => Some(url),
+            Repository::Other(_) => None,
+        }
+    }
+}
+
+/// A sample the Package Manager window offers to import
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Sample {
+    #[serde(
+        rename = "displayName",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub display_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Folder below the package, usually in `Samples~/`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct UpmCi {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub footprint: Option<String>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Upm {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub changelog: Option<String>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Reads `package/package.json` from a build or extracted template.
+pub fn read_package(package_path: &Path) -> std::io::Result<Data> {
+    let contents = fs::read_to_string(package_path.join("package.json"))?;
+    Ok(Data::from_value(serde_json::from_str(&contents)?)?)
+}
+
+pub fn write_package(package_path: &Path, data: &Data) -> std::io::Result<()> {
+    fs::write(
+        package_path.join("package.json"),
+        serde_json::to_string_pretty(&data.to_value()?)?,
+    )
+}
+
+pub fn build(
+    project_path: &str,
+    build_name: &str,
+    data: Data,
+    documents: Documents,
+    images: &[Image],
+) {
     // 1. build a tar file with all contents
     // 2. build a tar.gz file
     // 3. map to a tgz file
@@ -60,12 +292,9 @@ pub fn build(project_path: &str, data: Data) {
         "package\\README.md",
     ];
 
-    let path = format!(
-        ".\\builds\\com.unity.template.{}-{}\\",
-        data.name, data.version
-    );
+    let path = format!(".\\builds\\{}\\", build_name);
 
-    if let Ok(_) = fs::read_dir(&path) {
+    if fs::read_dir(&path).is_ok() {
         fs::remove_dir_all(&path)
             .unwrap_or_else(|_| panic!("Failed to remove build directory at {}", path));
     }
@@ -76,7 +305,7 @@ pub fn build(project_path: &str, data: Data) {
 
     // make folders
     for folder in folders.map(|dir| root_dir.join(dir)) {
-        let _ = fs::create_dir_all(&folder)
+        fs::create_dir_all(&folder)
             .unwrap_or_else(|_| panic!("Failed to create build directory at {:?}", folder));
     }
 
@@ -87,10 +316,20 @@ pub fn build(project_path: &str, data: Data) {
             .unwrap_or_else(|| panic!("Failed to get file extension at {:?}", file));
 
         let contents = match extension.to_str().unwrap() {
-            "md" => Some(String::new()),
+            "md" => match file.file_name() {
+                Some(name) => match name.to_str().unwrap() {
+                    "CHANGELOG.md" => Some(documents.changelog.clone()),
+                    "LICENSE.md" => Some(documents.license.clone()),
+                    "README.md" => Some(documents.readme.clone()),
+                    _ => Some(String::new()),
+                },
+                None => None,
+            },
             "json" => match file.file_name() {
                 Some(name) => match name.to_str().unwrap() {
-                    "package.json" => Some(serde_json::to_string_pretty(&data).unwrap()),
+                    "package.json" => {
+                        Some(serde_json::to_string_pretty(&data.to_value().unwrap()).unwrap())
+                    }
                     _ => Some("{}".into()),
                 },
                 None => None,
@@ -108,6 +347,14 @@ pub fn build(project_path: &str, data: Data) {
         }
     }
 
+    // copy images
+    for image in images {
+        let target = root_dir.join(format!("package\\{}", image.target.replace('/', "\\")));
+
+        fs::copy(&image.source, &target)
+            .unwrap_or_else(|_| panic!("Failed to copy image to {:?}", target));
+    }
+
     // copy project to dir
     let folders = ["Assets", "Packages", "ProjectSettings"];
 
@@ -139,27 +386,23 @@ pub fn build(project_path: &str, data: Data) {
     ];
 
     for file in files.map(|file| root_dir.join(file)) {
+        // forked templates don't always ship these
+        if !file.exists() {
+            continue;
+        }
+
         fs::remove_file(&file).unwrap_or_else(|_| panic!("Failed to remove file at {:?}", file));
     }
 
     // copy deps into manifest.json
-
-    #[derive(Serialize)]
-    struct Dependencies {
-        dependencies: serde_json::Value,
-    }
-
-    let deps = serde_json::to_string_pretty(&Dependencies {
-        dependencies: data.dependencies.clone(),
-    })
-    .unwrap();
-
-    let manifest_path = root_dir.join("package\\ProjectData~\\Packages\\manifest.json");
-    fs::write(&manifest_path, &deps)
-        .unwrap_or_else(|_| panic!("Failed to write manifest file at {:?}", manifest_path));
-    let manifest_path = root_dir.join("package\\ProjectData~\\Assets\\manifest.json");
-    fs::write(&manifest_path, &deps)
-        .unwrap_or_else(|_| panic!("Failed to write manifest file at {:?}", manifest_path));
+    let project_data = root_dir.join("package\\ProjectData~");
+    write_manifest(
+        &project_data,
+        &serde_json::json!({
+            "dependencies": data.dependencies.clone().unwrap_or_else(|| serde_json::json!({}))
+        }),
+    )
+    .unwrap_or_else(|_| panic!("Failed to write manifest files in {:?}", project_data));
 
     println!();
     println!("Build folder is located at:");
@@ -175,10 +418,166 @@ pub fn build(project_path: &str, data: Data) {
     // Command::new("explorer").arg(path).spawn().unwrap();
 }
 
+/// Checks a full package name against the UPM naming rules.
+pub fn validate_package_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("package name is empty".into());
+    }
+
+    if name.len() > MAX_NAME_LENGTH {
+        return Err(format!(
+            "package name is {} characters long, the limit is {}",
+            name.len(),
+            MAX_NAME_LENGTH
+        ));
+    }
+
+    if let Some(c) = name
+        .chars()
+        .find(|c| !matches!(c, 'a'..='z' | '0'..='9' | '.' | '-' | '_'))
+    {
+        return Err(format!(
+            "package name `{}` contains `{}`, only lowercase letters, digits, `.`, `-` and `_` are allowed",
+            name, c
+        ));
+    }
+
+    if name.split('.').any(|part| part.is_empty()) {
+        return Err(format!(
+            "package name `{}` has an empty segment between periods",
+            name
+        ));
+    }
+
+    if !name.starts_with(|c: char| c.is_ascii_lowercase()) {
+        return Err(format!(
+            "package name `{}` must start with a lowercase letter",
+            name
+        ));
+    }
+
+    Ok(())
+}
+
+/// Checks that a configured prefix still gives names the Hub lists as templates,
+/// which are shaped like `com.<company>.template.<name>`.
+pub fn validate_package_prefix(prefix: &str) -> Result<(), String> {
+    if !prefix.ends_with(".template.") || prefix.split('.').count() < 4 {
+        return Err(format!(
+            "package prefix `{}` should look like `com.<company>.template.` for the Hub to list it",
+            prefix
+        ));
+    }
+
+    validate_package_name(prefix.trim_end_matches('.'))
+}
+
+/// Every `.unity` scene below `Assets/`, as editor paths like `Assets/Scenes/SampleScene.unity`.
+pub fn find_scenes(project_path: &Path) -> Vec<String> {
+    let mut scenes = Vec::new();
+    let mut stack = vec![project_path.join("Assets")];
+
+    while let Some(dir) = stack.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            if path.is_dir() {
+                stack.push(path);
+            } else if path.extension().is_some_and(|ext| ext == "unity") {
+                let relative = path.strip_prefix(project_path).unwrap();
+                let scene: Vec<String> = relative
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy().to_string())
+                    .collect();
+                scenes.push(scene.join("/"));
+            }
+        }
+    }
+
+    scenes.sort();
+    scenes
+}
+
+/// Writes `Packages/manifest.json` and the copy in `Assets/` that the override script swaps in.
+pub fn write_manifest(project_data: &Path, manifest: &serde_json::Value) -> std::io::Result<()> {
+    let contents = serde_json::to_string_pretty(manifest)?;
+
+    fs::write(
+        project_data.join("Packages").join("manifest.json"),
+        &contents,
+    )?;
+    fs::write(project_data.join("Assets").join("manifest.json"), &contents)
+}
+
+/// Puts the real manifest back into `Packages/` after extracting a template,
+/// or keeps a copy in `Assets/` for the override script if there is none.
+pub fn restore_manifest(project_data: &Path) -> std::io::Result<()> {
+    let packages_manifest = project_data.join("Packages").join("manifest.json");
+    let assets_manifest = project_data.join("Assets").join("manifest.json");
+
+    if assets_manifest.exists() {
+        fs::copy(&assets_manifest, &packages_manifest)?;
+    } else if packages_manifest.exists() {
+        fs::copy(&packages_manifest, &assets_manifest)?;
+    }
+
+    Ok(())
+}
+
+/// The single-ran script that swaps in the real manifest and opens the default scene.
+pub fn bootstrap_script(default_scene: &str) -> String {
+    r#"
+using System.IO;
+using UnityEditor;
+using UnityEngine;
+
+public static class ___ManifestOverride
+{
+    private static readonly string InputPath = $"{Application.dataPath}/manifest.json";
+
+    [InitializeOnLoadMethod]
+    private static void OnLoad()
+    {
+        EditorApplication.delayCall += () => {
+            var fullScenePath = "FULL_SCENE_PATH";
+            if (!string.IsNullOrEmpty(fullScenePath)) {
+                Debug.LogWarning($"Attempting to open: {fullScenePath}");
+                UnityEditor.SceneManagement.EditorSceneManager.OpenScene(fullScenePath);
+            }
+
+            if (!File.Exists(InputPath))
+            {
+                Debug.LogWarning($"Input path does not exist at: {InputPath}");
+                Debug.LogWarning("___ManifestOverride.cs is most likely completed. Remove this file if so.");
+                return;
+            }
+
+            var targetPath = Path.Join(Application.dataPath, "..\\Packages\\manifest.json");
+
+            File.Copy(InputPath, targetPath, true);
+
+            // delete this file
+            AssetDatabase.DeleteAsset("Assets/___ManifestOverride.cs");
+            AssetDatabase.DeleteAsset("Assets/manifest.json");
+            AssetDatabase.SaveAssets();
+            AssetDatabase.Refresh();
+        };
+    }
+}
+    "#
+    .trim_start()
+    .replace("FULL_SCENE_PATH", default_scene)
+}
+
 // A simple implementation of `% touch path` (ignores existing files)
 fn touch(path: &Path) -> std::io::Result<()> {
     match std::fs::OpenOptions::new()
         .create(true)
+        .truncate(false)
         .write(true)
         .open(path)
     {
@@ -187,7 +586,7 @@ fn touch(path: &Path) -> std::io::Result<()> {
     }
 }
 
-fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> std::io::Result<()> {
+pub fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> std::io::Result<()> {
     fs::create_dir_all(&dst)?;
     for entry in fs::read_dir(src)? {
         let entry = entry?;
@@ -200,3 +599,130 @@ fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> std::io::Result
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(package: serde_json::Value) -> serde_json::Value {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("package.json"), package.to_string()).unwrap();
+
+        let data = read_package(dir.path()).unwrap();
+        write_package(dir.path(), &data).unwrap();
+
+        serde_json::from_str(&fs::read_to_string(dir.path().join("package.json")).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn round_trips_unity_template() {
+        let package = serde_json::json!({
+            "name": "com.unity.template.3d",
+            "displayName": "3D",
+            "version": "8.1.0",
+            "type": "template",
+            "host": "hub",
+            "unity": "2022.3",
+            "unityFull": "2022.3.5f1",
+            "description": "This is an empty 3D project that uses Unity's built-in renderer.",
+            "dependencies": {
+                "com.unity.ide.rider": "3.0.24",
+                "com.unity.modules.physics": "1.0.0"
+            },
+            "keywords": ["3d", "built-in"],
+            "category": "",
+            "defaultScene": "Assets/Scenes/SampleScene.unity",
+            "author": { "name": "Unity Technologies", "url": "https://unity.com" },
+            "repository": {
+                "type": "git",
+                "url": "https://github.cds.internal.unity3d.com/unity/com.unity.template-3d.git",
+                "revision": "a1b2c3",
+                "footprint": "d4e5f6"
+            },
+            "samples": [{ "displayName": "Demo", "path": "Samples~/Demo" }],
+            "upmCi": { "footprint": "0123abcd" },
+            "_upm": { "changelog": "- Updated packages" },
+            "_fingerprint": "789abc",
+            "hideInEditor": true
+        });
+
+        assert_eq!(round_trip(package.clone()), package);
+    }
+
+    #[test]
+    fn round_trips_partial_package() {
+        // nothing is added for missing fields
+        let package = serde_json::json!({ "name": "com.acme.template.tiny", "version": "0.1.0" });
+        assert_eq!(round_trip(package.clone()), package);
+
+        // shapes this tool doesn't model are kept as they were
+        let package = serde_json::json!({
+            "name": "com.acme.template.odd",
+            "version": "0.1.0",
+            "author": { "email": "dev@acme.com" },
+            "repository": { "type": "git" }
+        });
+        assert_eq!(round_trip(package.clone()), package);
+
+        // unknown sub-fields and explicit nulls are kept too
+        let package = serde_json::json!({
+            "name": "com.acme.template.extra",
+            "version": "0.1.0",
+            "description": null,
+            "author": { "name": "Acme", "email": "dev@acme.com", "twitter": "@acme" },
+            "samples": [{
+                "displayName": "Intro",
+                "description": "A first scene",
+                "path": "Samples~/Intro",
+                "interactiveImport": false
+            }],
+            "upmCi": { "footprint": "0a1b2c", "runner": "linux" },
+            "_upm": { "changelog": "Fixed things", "gameService": {} }
+        });
+        assert_eq!(round_trip(package.clone()), package);
+    }
+
+    #[test]
+    fn set_fields_replace_nulls() {
+        let package = serde_json::json!({
+            "name": "com.acme.template.tiny",
+            "version": "0.1.0",
+            "description": null
+        });
+
+        let mut data = Data::from_value(package).unwrap();
+        data.description = Some("A tiny template".to_string());
+
+        assert_eq!(
+            data.to_value().unwrap(),
+            serde_json::json!({
+                "name": "com.acme.template.tiny",
+                "version": "0.1.0",
+                "description": "A tiny template"
+            })
+        );
+    }
+
+    #[test]
+    fn package_names() {
+        assert!(validate_package_name("com.unity.template.my-game_2").is_ok());
+
+        assert!(validate_package_name("").is_err());
+        assert!(validate_package_name("com.Unity.template.game").is_err());
+        assert!(validate_package_name("com.unity.template game").is_err());
+        assert!(validate_package_name("com..template.game").is_err());
+        assert!(validate_package_name("com.unity.template.").is_err());
+        assert!(validate_package_name("1com.unity.template.game").is_err());
+        assert!(validate_package_name(&"a".repeat(MAX_NAME_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn package_prefixes() {
+        assert!(validate_package_prefix("com.unity.template.").is_ok());
+        assert!(validate_package_prefix("com.ourstudio.template.").is_ok());
+
+        assert!(validate_package_prefix("com.ourstudio.").is_err());
+        assert!(validate_package_prefix("template.").is_err());
+        assert!(validate_package_prefix("com.Our.template.").is_err());
+    }
+}
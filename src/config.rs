@@ -1,13 +1,22 @@
 use serde_derive::Deserialize;
 use std::{
-    collections::HashSet,
+    collections::{BTreeMap, HashSet},
     fs::{self, DirEntry},
+    path::PathBuf,
     process::exit,
 };
 
 #[derive(Deserialize)]
 pub struct Config {
     pub essentials: Essentials,
+    #[serde(default)]
+    pub documents: Documents,
+    #[serde(default)]
+    pub images: Images,
+    #[serde(default)]
+    pub presets: BTreeMap<String, Preset>,
+    #[serde(default)]
+    pub registry: Registry,
 }
 
 impl Config {
@@ -25,7 +34,7 @@ impl Config {
         )
     }
 
-    pub fn _get_built_in_packages_folder(&self, version: &Version) -> String {
+    pub fn get_built_in_packages_folder(&self, version: &Version) -> String {
         format!(
             "{}\\{}.{}\\Editor\\Data\\Resources\\PackageManager\\BuiltInPackages\\",
             self.essentials.unity_hub_path, version.major, version.minor
@@ -44,6 +53,51 @@ impl Config {
 pub struct Essentials {
     pub unity_hub_path: String,
     pub default_dependencies: Vec<String>,
+    #[serde(default = "default_package_prefix")]
+    pub package_prefix: String,
+}
+
+fn default_package_prefix() -> String {
+    crate::bundle::PACKAGE_PREFIX.to_string()
+}
+
+/// Sources for the generated README, CHANGELOG and LICENSE
+#[derive(Deserialize, Default)]
+pub struct Documents {
+    /// SPDX id to pre-select, or `custom` for `license_template`
+    pub license: Option<String>,
+    pub license_holder: Option<String>,
+    pub readme_template: Option<String>,
+    pub changelog_template: Option<String>,
+    pub license_template: Option<String>,
+}
+
+/// Images shown for the template in the Hub, overridden by `new --preview` and `--thumbnail`
+#[derive(Deserialize, Default)]
+pub struct Images {
+    pub preview: Option<String>,
+    pub thumbnail: Option<String>,
+}
+
+/// npm-compatible registry that `publish` uploads templates to
+#[derive(Deserialize, Default)]
+pub struct Registry {
+    pub url: Option<String>,
+    /// Environment variable holding the auth token
+    pub token_env: Option<String>,
+}
+
+/// A named set of dependencies to pre-select, such as `[presets.mobile]`
+#[derive(Deserialize)]
+pub struct Preset {
+    #[serde(default)]
+    pub packages: Vec<String>,
+    /// Versions to use instead of the resolved ones, these packages are selected too
+    #[serde(default)]
+    pub pins: BTreeMap<String, String>,
+    /// Presets whose packages and pins are included first
+    #[serde(default)]
+    pub extends: Vec<String>,
 }
 
 #[derive(PartialEq, Eq, Hash, Clone)]
@@ -55,12 +109,16 @@ pub struct Version {
 impl Version {
     pub fn parse(dir: &DirEntry) -> Version {
         let name = dir.file_name();
-        let name = &name.to_str().unwrap();
-        let last_index = name.rfind('.').unwrap();
+        Version::from_name(name.to_str().unwrap()).unwrap()
+    }
+
+    /// Parses an editor folder name such as `2021.3.16f1`.
+    pub fn from_name(name: &str) -> Option<Version> {
+        let last_index = name.rfind('.')?;
         let major = name[..last_index].to_string();
         let minor = name[(last_index + 1)..].to_string();
 
-        Version { major, minor }
+        Some(Version { major, minor })
     }
 }
 
@@ -91,6 +149,38 @@ default_dependencies=[
     "com.unity.visualscripting",
     "com.unity.ugui",
 ]
+# prefix for template package names, the hub expects `com.<company>.template.`
+package_prefix="com.unity.template."
+
+[documents]
+# license to pre-select, one of MIT, BSD-2-Clause, BSD-3-Clause, ISC, Unlicense, UNLICENSED or custom
+license="MIT"
+# name in the copyright line, defaults to "<display name> authors"
+# license_holder="Your Studio"
+# handlebars templates to use instead of the built-in ones
+# readme_template="templates/README.md"
+# changelog_template="templates/CHANGELOG.md"
+# license_template="templates/LICENSE.md"
+
+[images]
+# png or jpeg shown when the template is selected, up to 1920x1080
+# preview="images/preview.png"
+# square png or jpeg shown in the template list, up to 512x512
+# thumbnail="images/thumbnail.png"
+
+[registry]
+# npm-compatible registry that `publish` uploads to, such as a Verdaccio server
+# url="https://upm.example.com"
+# environment variable holding the registry auth token
+token_env="UPM_REGISTRY_TOKEN"
+
+# named sets of dependencies to pre-select, picked with `new --preset <name>`
+# [presets.urp]
+# packages=["com.unity.render-pipelines.universal"]
+# [presets.mobile]
+# extends=["urp"]
+# packages=["com.unity.mobile.notifications"]
+# pins={ "com.unity.render-pipelines.universal"="14.0.8" }
             "#
             .trim_start();
 
@@ -163,7 +253,8 @@ pub fn load_dependencies_from(path: &str) -> Vec<BuiltInPackage> {
     deps.iter()
         .map(|dep| BuiltInPackage {
             name: Some(dep.0.to_owned().to_string()),
-            version: Some(dep.1.to_owned().to_string()),
+            // `to_string` would keep the json quotes
+            version: dep.1.as_str().map(|version| version.to_string()),
         })
         .collect()
 }
@@ -180,7 +271,7 @@ pub fn load_dependencies(config: &Config, version: &Version) -> Vec<BuiltInPacka
     };
 
     files
-        .map(|file| {
+        .filter_map(|file| {
             let dir = match file {
                 Ok(file) => file,
                 Err(_) => return None,
@@ -193,14 +284,57 @@ pub fn load_dependencies(config: &Config, version: &Version) -> Vec<BuiltInPacka
                 None => return None,
             };
             let name = file_name[..dash_index].to_string();
-            let version = file_name[(dash_index + 1)..].to_string();
+            // bundled packages are `<name>-<version>.tgz`
+            let version = file_name[(dash_index + 1)..]
+                .trim_end_matches(".tgz")
+                .to_string();
 
             Some(BuiltInPackage {
                 name: Some(name),
                 version: Some(version),
             })
         })
-        .filter(|file| file.is_some())
-        .map(|file| file.unwrap())
         .collect()
 }
+
+/// All template `.tgz` files that ship with an editor, such as `com.unity.template.3d-5.0.4.tgz`.
+pub fn load_templates(config: &Config, version: &Version) -> Vec<PathBuf> {
+    let path = config.get_template_folder(&format!("{}.{}", version.major, version.minor));
+    let files = match fs::read_dir(path) {
+        Ok(files) => files,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut templates: Vec<PathBuf> = files
+        .filter_map(|file| file.ok())
+        .map(|file| file.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "tgz"))
+        .collect();
+    templates.sort();
+
+    templates
+}
+
+/// Module packages the editor ships in `BuiltInPackages`, such as `com.unity.modules.audio`.
+pub fn load_modules(config: &Config, version: &Version) -> Vec<PathBuf> {
+    let path = config.get_built_in_packages_folder(version);
+    let dirs = match fs::read_dir(path) {
+        Ok(dirs) => dirs,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut modules: Vec<PathBuf> = dirs
+        .filter_map(|dir| dir.ok())
+        .map(|dir| dir.path())
+        .filter(|path| {
+            path.is_dir()
+                && path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(|name| name.starts_with(crate::deps::MODULE_PREFIX))
+        })
+        .collect();
+    modules.sort();
+
+    modules
+}
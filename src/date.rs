@@ -0,0 +1,80 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub struct Date {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+impl Date {
+    /// Converts seconds since the unix epoch to a UTC date.
+    pub fn from_unix(secs: u64) -> Date {
+        let days = (secs / 86400) as i64;
+        let seconds = (secs % 86400) as u32;
+
+        // days since 1970-01-01 to a civil date (Howard Hinnant's algorithm)
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z - era * 146097;
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        Date {
+            year,
+            month: month as u32,
+            day: day as u32,
+            hour: seconds / 3600,
+            minute: seconds % 3600 / 60,
+            second: seconds % 60,
+        }
+    }
+
+    pub fn today() -> Date {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+
+        Date::from_unix(now)
+    }
+
+    /// `YYYY-MM-DD`, as used by Keep a Changelog
+    pub fn to_iso(&self) -> String {
+        format!("{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(date: &Date) -> String {
+        format!(
+            "{}T{:02}:{:02}:{:02}",
+            date.to_iso(),
+            date.hour,
+            date.minute,
+            date.second
+        )
+    }
+
+    #[test]
+    fn from_unix() {
+        assert_eq!(format(&Date::from_unix(0)), "1970-01-01T00:00:00");
+        assert_eq!(format(&Date::from_unix(499162500)), "1985-10-26T08:15:00");
+        assert_eq!(format(&Date::from_unix(1700000000)), "2023-11-14T22:13:20");
+    }
+
+    #[test]
+    fn from_unix_leap_day() {
+        assert_eq!(format(&Date::from_unix(951782400)), "2000-02-29T00:00:00");
+        assert_eq!(format(&Date::from_unix(951868800)), "2000-03-01T00:00:00");
+    }
+}
@@ -0,0 +1,728 @@
+use crate::{archive, config, git};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+/// Package names to versions
+pub type Packages = BTreeMap<String, String>;
+
+pub const MODULE_PREFIX: &str = "com.unity.modules.";
+
+/// Modules are built into the editor and always written with this version
+pub const MODULE_VERSION: &str = "1.0.0";
+
+/// Modules most projects need, the editor's own UI and packages depend on these
+pub const MINIMAL_MODULES: [&str; 6] = [
+    "com.unity.modules.audio",
+    "com.unity.modules.imgui",
+    "com.unity.modules.jsonserialize",
+    "com.unity.modules.physics",
+    "com.unity.modules.ui",
+    "com.unity.modules.uielements",
+];
+
+pub fn is_module(name: &str) -> bool {
+    name.starts_with(MODULE_PREFIX)
+}
+
+/// Modules bundled with an editor, with their display names.
+pub fn editor_modules(
+    config: &config::Config,
+    editor: &config::Version,
+) -> BTreeMap<String, Option<String>> {
+    config::load_modules(config, editor)
+        .into_iter()
+        .map(|path| {
+            let name = path.file_name().unwrap().to_str().unwrap().to_string();
+            let display_name = std::fs::read_to_string(path.join("package.json"))
+                .ok()
+                .and_then(|contents| serde_json::from_str(&contents).ok())
+                .and_then(|package| display_name(&package));
+
+            (name, display_name)
+        })
+        .collect()
+}
+
+/// Packages bundled with an editor.
+pub fn editor_packages(config: &config::Config, editor: &config::Version) -> Packages {
+    collect(config::load_dependencies(config, editor))
+}
+
+/// Packages in a project's `Packages/manifest.json`.
+pub fn project_packages(project_path: &str) -> Packages {
+    collect(config::load_dependencies_from(project_path))
+}
+
+/// Versions the project's `Packages/packages-lock.json` resolved its direct dependencies to.
+///
+/// Git dependencies are pinned to their locked commit, and modules are left out.
+pub fn locked_packages(project_path: &str) -> Result<Packages, String> {
+    let path = format!("{}\\Packages\\packages-lock.json", project_path);
+    let contents =
+        std::fs::read_to_string(&path).map_err(|e| format!("could not read `{}`: {}", path, e))?;
+    let lock: serde_json::Value = serde_json::from_str(&contents)
+        .map_err(|e| format!("could not parse `{}`: {}", path, e))?;
+
+    let mut locked = Packages::new();
+    for (name, entry) in lock["dependencies"].as_object().into_iter().flatten() {
+        // depth 0 is what the manifest asked for, the rest are their dependencies
+        if entry["depth"].as_u64() != Some(0) || is_module(name) {
+            continue;
+        }
+
+        let Some(version) = entry["version"].as_str() else {
+            continue;
+        };
+
+        let version = match (entry["source"].as_str(), entry["hash"].as_str()) {
+            (Some("git"), Some(hash)) => {
+                let url = version.split('#').next().unwrap_or(version);
+                format!("{}#{}", url, hash)
+            }
+            _ => version.to_string(),
+        };
+
+        locked.insert(name.clone(), version);
+    }
+
+    Ok(locked)
+}
+
+fn collect(packages: Vec<config::BuiltInPackage>) -> Packages {
+    packages
+        .into_iter()
+        .filter_map(|package| Some((package.name?, package.version?)))
+        .collect()
+}
+
+/// Sections of the dependency picker
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Group {
+    Project,
+    Git,
+    Editor,
+    Features,
+}
+
+impl fmt::Display for Group {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Group::Project => write!(f, "From project"),
+            Group::Git => write!(f, "Git"),
+            Group::Editor => write!(f, "Editor built-in"),
+            Group::Features => write!(f, "Features"),
+        }
+    }
+}
+
+pub fn group(name: &str, project: &Packages) -> Group {
+    if name.starts_with("com.unity.feature.") {
+        Group::Features
+    } else if project
+        .get(name)
+        .is_some_and(|version| git::is_git(version))
+    {
+        Group::Git
+    } else if project.contains_key(name) {
+        Group::Project
+    } else {
+        Group::Editor
+    }
+}
+
+/// Looks up the `displayName` of each package from its package.json.
+///
+/// Bundled packages are read from the editor's `.tgz` files, and project packages from
+/// `Packages/` or `Library/PackageCache/` when the project has been opened before.
+pub fn display_names(
+    config: &config::Config,
+    editors: &[config::Version],
+    editor_packages: &[Packages],
+    project_path: &str,
+    project: &Packages,
+) -> BTreeMap<String, String> {
+    let mut names = BTreeMap::new();
+
+    for (name, version) in project {
+        let candidates = [
+            format!("{}\\Packages\\{}\\package.json", project_path, name),
+            format!(
+                "{}\\Library\\PackageCache\\{}@{}\\package.json",
+                project_path, name, version
+            ),
+        ];
+
+        let display_name = candidates.iter().find_map(|path| {
+            let contents = std::fs::read_to_string(path).ok()?;
+            display_name(&serde_json::from_str(&contents).ok()?)
+        });
+
+        if let Some(display_name) = display_name {
+            names.insert(name.clone(), display_name);
+        }
+    }
+
+    for (editor, packages) in editors.iter().zip(editor_packages) {
+        let folder = PathBuf::from(config.get_editor_folder(editor));
+
+        for (name, version) in packages {
+            if names.contains_key(name) {
+                continue;
+            }
+
+            let tgz = folder.join(format!("{}-{}.tgz", name, version));
+            if let Some(display_name) =
+                read_tgz_package(&tgz).and_then(|package| display_name(&package))
+            {
+                names.insert(name.clone(), display_name);
+            }
+        }
+    }
+
+    names
+}
+
+fn display_name(package: &serde_json::Value) -> Option<String> {
+    package["displayName"].as_str().map(|name| name.to_string())
+}
+
+pub fn read_tgz_package(path: &Path) -> Option<serde_json::Value> {
+    let mut archive = archive::open_tgz(path).ok()?;
+
+    for entry in archive.entries().ok()? {
+        let mut entry = entry.ok()?;
+        if archive::package_path(&entry.path().ok()?) != "package.json" {
+            continue;
+        }
+
+        let mut contents = String::new();
+        entry.read_to_string(&mut contents).ok()?;
+        return serde_json::from_str(&contents).ok();
+    }
+
+    None
+}
+
+/// Which version wins when a package is both in the project and bundled with the editor
+#[derive(Clone, Copy, Debug, Default, clap::ValueEnum)]
+pub enum Policy {
+    /// Keep the version the template was made with
+    #[default]
+    PreferProject,
+    /// Use the version the editor ships with, so no registry access is needed
+    PreferEditorBundled,
+    /// Use whichever version is higher
+    HighestSemver,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Project,
+    Editor,
+    /// Set by a preset, the project's lock file or a git revision picked for the project
+    Pinned,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Project => write!(f, "project"),
+            Source::Editor => write!(f, "editor"),
+            Source::Pinned => write!(f, "pinned"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct Resolved {
+    pub version: String,
+    pub source: Source,
+}
+
+/// Picks the version of `name` to use with an editor.
+///
+/// Pinned versions always win, then `policy` decides between the project and the editor.
+pub fn resolve(
+    name: &str,
+    project: &Packages,
+    editor: &Packages,
+    pins: &Packages,
+    policy: Policy,
+) -> Option<Resolved> {
+    if let Some(version) = pins.get(name) {
+        return Some(Resolved {
+            version: version.clone(),
+            source: Source::Pinned,
+        });
+    }
+
+    let from_project = project.get(name).map(|version| Resolved {
+        version: version.clone(),
+        source: Source::Project,
+    });
+    let from_editor = editor.get(name).map(|version| Resolved {
+        version: version.clone(),
+        source: Source::Editor,
+    });
+
+    match (from_project, from_editor) {
+        (Some(project), Some(editor)) => Some(match policy {
+            Policy::PreferProject => project,
+            Policy::PreferEditorBundled => editor,
+            // git and local packages can't be compared, keep them
+            Policy::HighestSemver => match (
+                semver::Version::parse(&project.version),
+                semver::Version::parse(&editor.version),
+            ) {
+                (Ok(a), Ok(b)) if b > a => editor,
+                _ => project,
+            },
+        }),
+        (project, editor) => project.or(editor),
+    }
+}
+
+/// Resolves every package in `names` for one editor.
+///
+/// Returns the resolved versions, and the names that editor does not provide. Registry packages
+/// it doesn't bundle at the resolved version are still resolved, as they come from the registry,
+/// while packages with no version at all are left out.
+pub fn resolve_all(
+    names: &[String],
+    project: &Packages,
+    editor: &Packages,
+    pins: &Packages,
+    policy: Policy,
+) -> (Packages, Vec<String>) {
+    let mut resolved = Packages::new();
+    let mut missing = Vec::new();
+
+    for name in names {
+        match resolve(name, project, editor, pins, policy) {
+            Some(package) => {
+                // git and local packages don't come from the editor
+                let is_registry = semver::Version::parse(&package.version).is_ok();
+                if is_registry && editor.get(name) != Some(&package.version) {
+                    missing.push(name.clone());
+                }
+                resolved.insert(name.clone(), package.version);
+            }
+            None => missing.push(name.clone()),
+        }
+    }
+
+    (resolved, missing)
+}
+
+/// Packages and pins of the named presets, including the presets they extend.
+///
+/// Pins from a preset override the ones it extends. A preset reached more than once is only
+/// expanded the first time, so it can't override a preset that extends it.
+pub fn expand_presets(
+    presets: &BTreeMap<String, config::Preset>,
+    names: &[String],
+) -> Result<(Vec<String>, Packages), String> {
+    fn visit(
+        presets: &BTreeMap<String, config::Preset>,
+        name: &str,
+        stack: &mut Vec<String>,
+        done: &mut BTreeSet<String>,
+        packages: &mut Vec<String>,
+        pins: &mut Packages,
+    ) -> Result<(), String> {
+        if stack.iter().any(|visiting| visiting == name) {
+            return Err(format!(
+                "presets extend each other in a loop: {} -> {}",
+                stack.join(" -> "),
+                name
+            ));
+        }
+        if done.contains(name) {
+            return Ok(());
+        }
+
+        let preset = presets
+            .get(name)
+            .ok_or_else(|| format!("unknown preset `{}`", name))?;
+
+        stack.push(name.to_string());
+        for parent in &preset.extends {
+            visit(presets, parent, stack, done, packages, pins)?;
+        }
+        stack.pop();
+        done.insert(name.to_string());
+
+        for package in preset.packages.iter().chain(preset.pins.keys()) {
+            if !packages.contains(package) {
+                packages.push(package.clone());
+            }
+        }
+        pins.extend(preset.pins.clone());
+
+        Ok(())
+    }
+
+    let mut packages = Vec::new();
+    let mut pins = Packages::new();
+    let mut done = BTreeSet::new();
+    for name in names {
+        visit(
+            presets,
+            name,
+            &mut Vec::new(),
+            &mut done,
+            &mut packages,
+            &mut pins,
+        )?;
+    }
+
+    Ok((packages, pins))
+}
+
+/// What happened to a dependency when moving to another editor
+pub enum Change {
+    Unchanged,
+    Upgraded(String),
+    Downgraded(String),
+    /// Not bundled with the new editor, kept at its version so it comes from the registry
+    NotBundled,
+    /// Not bundled with the new editor, and removed as asked
+    Removed,
+    /// Modules, local and git packages don't depend on the editor's bundled versions
+    Kept,
+}
+
+/// Moves a manifest's dependencies to the versions bundled with another editor.
+///
+/// Registry packages the editor doesn't bundle keep their version, unless `remove_unbundled` is set.
+/// Returns the new dependencies, and what happened to each of the old ones.
+pub fn remap(
+    dependencies: &Packages,
+    editor: &Packages,
+    remove_unbundled: bool,
+) -> (Packages, Vec<(String, Change)>) {
+    let mut remapped = Packages::new();
+    let mut changes = Vec::new();
+
+    for (name, version) in dependencies {
+        let is_registry = semver::Version::parse(version).is_ok();
+
+        let change = if is_module(name) || !is_registry {
+            remapped.insert(name.clone(), version.clone());
+            Change::Kept
+        } else {
+            match editor.get(name) {
+                Some(bundled) => {
+                    remapped.insert(name.clone(), bundled.clone());
+                    match compare(version, bundled) {
+                        Ordering::Equal => Change::Unchanged,
+                        Ordering::Less => Change::Upgraded(bundled.clone()),
+                        Ordering::Greater => Change::Downgraded(bundled.clone()),
+                    }
+                }
+                None if remove_unbundled => Change::Removed,
+                None => {
+                    remapped.insert(name.clone(), version.clone());
+                    Change::NotBundled
+                }
+            }
+        };
+
+        changes.push((name.clone(), change));
+    }
+
+    (remapped, changes)
+}
+
+// bundled versions are usually semver, fall back to comparing text
+fn compare(a: &str, b: &str) -> Ordering {
+    match (semver::Version::parse(a), semver::Version::parse(b)) {
+        (Ok(a), Ok(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn packages(list: &[(&str, &str)]) -> Packages {
+        list.iter()
+            .map(|(name, version)| (name.to_string(), version.to_string()))
+            .collect()
+    }
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn resolves_against_each_editor() {
+        let project = packages(&[
+            ("com.unity.timeline", "1.7.0"),
+            ("com.unity.cinemachine", "2.9.7"),
+            ("com.acme.tools", "https://github.com/acme/tools.git#v1"),
+        ]);
+        let editor = packages(&[("com.unity.timeline", "1.6.4"), ("com.unity.ugui", "1.0.0")]);
+        let selected = names(&[
+            "com.unity.timeline",
+            "com.unity.cinemachine",
+            "com.acme.tools",
+            "com.unity.ugui",
+            "com.unity.2d.sprite",
+        ]);
+
+        let (resolved, missing) = resolve_all(
+            &selected,
+            &project,
+            &editor,
+            &Packages::new(),
+            Policy::PreferProject,
+        );
+
+        assert_eq!(
+            resolved,
+            packages(&[
+                ("com.unity.timeline", "1.7.0"),
+                ("com.unity.cinemachine", "2.9.7"),
+                ("com.acme.tools", "https://github.com/acme/tools.git#v1"),
+                ("com.unity.ugui", "1.0.0"),
+            ])
+        );
+        // a different version, not bundled at all, and not available anywhere
+        assert_eq!(
+            missing,
+            names(&[
+                "com.unity.timeline",
+                "com.unity.cinemachine",
+                "com.unity.2d.sprite"
+            ])
+        );
+    }
+
+    #[test]
+    fn policy_picks_between_versions() {
+        let project = packages(&[("com.unity.timeline", "1.7.0"), ("com.unity.ugui", "1.0.0")]);
+        let editor = packages(&[("com.unity.timeline", "1.6.4"), ("com.unity.ugui", "2.0.0")]);
+        let selected = names(&["com.unity.timeline", "com.unity.ugui"]);
+        let none = Packages::new();
+
+        let (resolved, missing) = resolve_all(
+            &selected,
+            &project,
+            &editor,
+            &none,
+            Policy::PreferEditorBundled,
+        );
+        assert_eq!(resolved, editor);
+        assert!(missing.is_empty());
+
+        let (resolved, missing) =
+            resolve_all(&selected, &project, &editor, &none, Policy::HighestSemver);
+        assert_eq!(
+            resolved,
+            packages(&[("com.unity.timeline", "1.7.0"), ("com.unity.ugui", "2.0.0")])
+        );
+        assert_eq!(missing, names(&["com.unity.timeline"]));
+
+        let pins = packages(&[("com.unity.ugui", "1.0.0")]);
+        let (resolved, missing) = resolve_all(
+            &selected,
+            &project,
+            &editor,
+            &pins,
+            Policy::PreferEditorBundled,
+        );
+        assert_eq!(resolved["com.unity.ugui"], "1.0.0");
+        assert_eq!(missing, names(&["com.unity.ugui"]));
+    }
+
+    #[test]
+    fn remap_keeps_unbundled_packages() {
+        let dependencies = packages(&[
+            ("com.unity.timeline", "1.6.4"),
+            ("com.unity.cinemachine", "2.9.7"),
+            ("com.unity.modules.ui", "1.0.0"),
+            ("com.acme.tools", "file:../tools"),
+        ]);
+        let editor = packages(&[("com.unity.timeline", "1.7.5")]);
+
+        let (remapped, changes) = remap(&dependencies, &editor, false);
+        assert_eq!(
+            remapped,
+            packages(&[
+                ("com.unity.timeline", "1.7.5"),
+                ("com.unity.cinemachine", "2.9.7"),
+                ("com.unity.modules.ui", "1.0.0"),
+                ("com.acme.tools", "file:../tools"),
+            ])
+        );
+        assert!(changes
+            .iter()
+            .any(|(name, change)| name == "com.unity.cinemachine"
+                && matches!(change, Change::NotBundled)));
+
+        let (remapped, changes) = remap(&dependencies, &editor, true);
+        assert!(!remapped.contains_key("com.unity.cinemachine"));
+        assert!(changes
+            .iter()
+            .any(|(name, change)| name == "com.unity.cinemachine"
+                && matches!(change, Change::Removed)));
+    }
+
+    fn presets(toml: &str) -> BTreeMap<String, config::Preset> {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn presets_include_what_they_extend_first() {
+        let presets = presets(
+            r#"
+            [base]
+            packages = ["com.unity.ugui"]
+
+            [mobile]
+            extends = ["base"]
+            packages = ["com.unity.mobile.notifications"]
+            pins = { "com.unity.addressables" = "1.21.19" }
+            "#,
+        );
+
+        let (packages, pins) = expand_presets(&presets, &names(&["mobile"])).unwrap();
+        assert_eq!(
+            packages,
+            names(&[
+                "com.unity.ugui",
+                "com.unity.mobile.notifications",
+                "com.unity.addressables",
+            ])
+        );
+        assert_eq!(
+            pins,
+            self::packages(&[("com.unity.addressables", "1.21.19")])
+        );
+    }
+
+    #[test]
+    fn preset_pins_override_extended_ones() {
+        let presets = presets(
+            r#"
+            [base]
+            pins = { "com.unity.timeline" = "1.6.4", "com.unity.ugui" = "1.0.0" }
+
+            [child]
+            extends = ["base"]
+            pins = { "com.unity.timeline" = "1.7.5" }
+            "#,
+        );
+
+        let (_, pins) = expand_presets(&presets, &names(&["child"])).unwrap();
+        assert_eq!(
+            pins,
+            packages(&[("com.unity.timeline", "1.7.5"), ("com.unity.ugui", "1.0.0"),])
+        );
+    }
+
+    #[test]
+    fn presets_extending_the_same_preset() {
+        let presets = presets(
+            r#"
+            [base]
+            packages = ["com.unity.ugui"]
+            pins = { "com.unity.timeline" = "1.6.4" }
+
+            [left]
+            extends = ["base"]
+            pins = { "com.unity.timeline" = "1.7.5" }
+
+            [right]
+            extends = ["base"]
+            packages = ["com.unity.cinemachine"]
+
+            [top]
+            extends = ["left", "right"]
+            "#,
+        );
+
+        let (packages, pins) = expand_presets(&presets, &names(&["top"])).unwrap();
+        assert_eq!(
+            packages,
+            names(&[
+                "com.unity.ugui",
+                "com.unity.timeline",
+                "com.unity.cinemachine",
+            ])
+        );
+        // reaching `base` again through `right` doesn't undo `left`'s pin
+        assert_eq!(pins, self::packages(&[("com.unity.timeline", "1.7.5")]));
+    }
+
+    #[test]
+    fn presets_extending_in_a_loop() {
+        let presets = presets(
+            r#"
+            [a]
+            extends = ["b"]
+
+            [b]
+            extends = ["a"]
+            "#,
+        );
+
+        let error = expand_presets(&presets, &names(&["a"])).unwrap_err();
+        assert_eq!(error, "presets extend each other in a loop: a -> b -> a");
+
+        let error = expand_presets(&presets, &names(&["missing"])).unwrap_err();
+        assert_eq!(error, "unknown preset `missing`");
+    }
+
+    #[test]
+    fn lock_pins_direct_dependencies() {
+        let dir = tempfile::tempdir().unwrap();
+        let project_path = dir.path().join("project").to_str().unwrap().to_string();
+        // built the same way `locked_packages` builds it
+        let lock_path = PathBuf::from(format!("{}\\Packages\\packages-lock.json", project_path));
+        std::fs::create_dir_all(lock_path.parent().unwrap()).unwrap();
+        std::fs::write(
+            &lock_path,
+            r#"{
+                "dependencies": {
+                    "com.unity.timeline": {
+                        "version": "1.7.5", "depth": 0, "source": "registry"
+                    },
+                    "com.unity.modules.audio": {
+                        "version": "1.0.0", "depth": 0, "source": "builtin"
+                    },
+                    "com.unity.mathematics": {
+                        "version": "1.2.6", "depth": 1, "source": "registry"
+                    },
+                    "com.acme.tools": {
+                        "version": "https://github.com/acme/tools.git?path=/Packages/tools#v1",
+                        "depth": 0,
+                        "source": "git",
+                        "hash": "0123abcd"
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            locked_packages(&project_path).unwrap(),
+            packages(&[
+                ("com.unity.timeline", "1.7.5"),
+                (
+                    "com.acme.tools",
+                    "https://github.com/acme/tools.git?path=/Packages/tools#0123abcd"
+                ),
+            ])
+        );
+
+        assert!(locked_packages(dir.path().join("missing").to_str().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,270 @@
+use crate::{bundle, config, date::Date};
+use handlebars::{no_escape, Handlebars};
+use serde_json::json;
+use std::fs;
+
+pub const CHANGELOG_HEADER: &str = r#"# Changelog
+
+All notable changes to this template will be documented in this file.
+
+The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.1.0/),
+and this project adheres to [Semantic Versioning](https://semver.org/spec/v2.0.0.html).
+"#;
+
+const README_TEMPLATE: &str = r#"# {{displayName}}
+
+{{description}}
+
+## Details
+
+- Package: `{{name}}`
+- Version: {{version}}
+- Unity: {{unityFull}}
+- Default scene: {{#if defaultScene}}`{{defaultScene}}`{{else}}none{{/if}}
+
+## Dependencies
+
+{{#each dependencies}}
+- `{{@key}}`: {{this}}
+{{else}}
+None.
+{{/each}}
+"#;
+
+const CHANGELOG_TEMPLATE: &str = r#"{{changelogHeader}}
+## [Unreleased]
+
+## [{{version}}] - {{date}}
+
+### Added
+
+- Initial release of {{displayName}}.
+"#;
+
+/// SPDX identifiers with a bundled license text
+pub const LICENSES: [&str; 6] = [
+    "MIT",
+    "BSD-2-Clause",
+    "BSD-3-Clause",
+    "ISC",
+    "Unlicense",
+    "UNLICENSED",
+];
+
+const MIT: &str = r#"MIT License
+
+Copyright (c) {{year}} {{licenseHolder}}
+
+Permission is hereby granted, free of charge, to any person obtaining a copy
+of this software and associated documentation files (the "Software"), to deal
+in the Software without restriction, including without limitation the rights
+to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+copies of the Software, and to permit persons to whom the Software is
+furnished to do so, subject to the following conditions:
+
+The above copyright notice and this permission notice shall be included in all
+copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+SOFTWARE.
+"#;
+
+const BSD_2_CLAUSE: &str = r#"BSD 2-Clause License
+
+Copyright (c) {{year}}, {{licenseHolder}}
+
+Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice, this
+   list of conditions and the following disclaimer.
+
+2. Redistributions in binary form must reproduce the above copyright notice,
+   this list of conditions and the following disclaimer in the documentation
+   and/or other materials provided with the distribution.
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+"#;
+
+const BSD_3_CLAUSE: &str = r#"BSD 3-Clause License
+
+Copyright (c) {{year}}, {{licenseHolder}}
+
+Redistribution and use in source and binary forms, with or without
+modification, are permitted provided that the following conditions are met:
+
+1. Redistributions of source code must retain the above copyright notice, this
+   list of conditions and the following disclaimer.
+
+2. Redistributions in binary form must reproduce the above copyright notice,
+   this list of conditions and the following disclaimer in the documentation
+   and/or other materials provided with the distribution.
+
+3. Neither the name of the copyright holder nor the names of its
+   contributors may be used to endorse or promote products derived from
+   this software without specific prior written permission.
+
+THIS SOFTWARE IS PROVIDED BY THE COPYRIGHT HOLDERS AND CONTRIBUTORS "AS IS"
+AND ANY EXPRESS OR IMPLIED WARRANTIES, INCLUDING, BUT NOT LIMITED TO, THE
+IMPLIED WARRANTIES OF MERCHANTABILITY AND FITNESS FOR A PARTICULAR PURPOSE ARE
+DISCLAIMED. IN NO EVENT SHALL THE COPYRIGHT HOLDER OR CONTRIBUTORS BE LIABLE
+FOR ANY DIRECT, INDIRECT, INCIDENTAL, SPECIAL, EXEMPLARY, OR CONSEQUENTIAL
+DAMAGES (INCLUDING, BUT NOT LIMITED TO, PROCUREMENT OF SUBSTITUTE GOODS OR
+SERVICES; LOSS OF USE, DATA, OR PROFITS; OR BUSINESS INTERRUPTION) HOWEVER
+CAUSED AND ON ANY THEORY OF LIABILITY, WHETHER IN CONTRACT, STRICT LIABILITY,
+OR TORT (INCLUDING NEGLIGENCE OR OTHERWISE) ARISING IN ANY WAY OUT OF THE USE
+OF THIS SOFTWARE, EVEN IF ADVISED OF THE POSSIBILITY OF SUCH DAMAGE.
+"#;
+
+const ISC: &str = r#"ISC License
+
+Copyright (c) {{year}}, {{licenseHolder}}
+
+Permission to use, copy, modify, and/or distribute this software for any
+purpose with or without fee is hereby granted, provided that the above
+copyright notice and this permission notice appear in all copies.
+
+THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+"#;
+
+const UNLICENSE: &str = r#"This is free and unencumbered software released into the public domain.
+
+Anyone is free to copy, modify, publish, use, compile, sell, or
+distribute this software, either in source code form or as a compiled
+binary, for any purpose, commercial or non-commercial, and by any
+means.
+
+In jurisdictions that recognize copyright laws, the author or authors
+of this software dedicate any and all copyright interest in the
+software to the public domain. We make this dedication for the benefit
+of the public at large and to the detriment of our heirs and
+successors. We intend this dedication to be an overt act of
+relinquishment in perpetuity of all present and future rights to this
+software under copyright law.
+
+THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND,
+EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF
+MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.
+IN NO EVENT SHALL THE AUTHORS BE LIABLE FOR ANY CLAIM, DAMAGES OR
+OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE,
+ARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR
+OTHER DEALINGS IN THE SOFTWARE.
+
+For more information, please refer to <https://unlicense.org>
+"#;
+
+const UNLICENSED: &str = r#"Copyright (c) {{year}} {{licenseHolder}}. All rights reserved.
+
+This template is proprietary and may not be copied, modified or
+distributed without prior written permission.
+"#;
+
+/// Rendered contents of the package's markdown files
+pub struct Documents {
+    pub readme: String,
+    pub changelog: String,
+    pub license: String,
+}
+
+/// Where the license text comes from
+pub enum License {
+    Spdx(String),
+    /// The `license_template` file from the config
+    Custom,
+}
+
+/// Renders the README, CHANGELOG and LICENSE for a build.
+///
+/// Templates from the `[documents]` config section replace the bundled ones.
+pub fn render(
+    config: &config::Config,
+    data: &bundle::Data,
+    license: &License,
+) -> Result<Documents, String> {
+    let documents = &config.documents;
+
+    let changelog = load_template(&documents.changelog_template, CHANGELOG_TEMPLATE)?;
+    let license = match license {
+        License::Spdx(id) => license_text(id)
+            .ok_or_else(|| format!("no bundled license text for `{}`", id))?
+            .to_string(),
+        License::Custom => load_template(&documents.license_template, "")?,
+    };
+
+    Ok(Documents {
+        readme: render_readme(config, data)?,
+        changelog: render_template(config, data, &changelog)?,
+        license: render_template(config, data, &license)?,
+    })
+}
+
+/// Renders only the README, for builds whose package.json changed after they were made.
+pub fn render_readme(config: &config::Config, data: &bundle::Data) -> Result<String, String> {
+    let readme = load_template(&config.documents.readme_template, README_TEMPLATE)?;
+    render_template(config, data, &readme)
+}
+
+fn render_template(
+    config: &config::Config,
+    data: &bundle::Data,
+    template: &str,
+) -> Result<String, String> {
+    let mut context = data.to_value().map_err(|e| e.to_string())?;
+    let today = Date::today();
+    context["year"] = json!(today.year);
+    context["date"] = json!(today.to_iso());
+    context["changelogHeader"] = json!(CHANGELOG_HEADER);
+    context["licenseHolder"] = json!(config
+        .documents
+        .license_holder
+        .clone()
+        .unwrap_or_else(|| format!("{} authors", data.display_name())));
+
+    let mut handlebars = Handlebars::new();
+    // markdown, not html
+    handlebars.register_escape_fn(no_escape);
+
+    handlebars
+        .render_template(template, &context)
+        .map_err(|e| e.to_string())
+}
+
+pub fn license_text(id: &str) -> Option<&'static str> {
+    match id {
+        "MIT" => Some(MIT),
+        "BSD-2-Clause" => Some(BSD_2_CLAUSE),
+        "BSD-3-Clause" => Some(BSD_3_CLAUSE),
+        "ISC" => Some(ISC),
+        "Unlicense" => Some(UNLICENSE),
+        "UNLICENSED" => Some(UNLICENSED),
+        _ => None,
+    }
+}
+
+fn load_template(path: &Option<String>, fallback: &str) -> Result<String, String> {
+    match path {
+        Some(path) => fs::read_to_string(path)
+            .map_err(|e| format!("could not read template `{}`: {}", path, e)),
+        None => Ok(fallback.to_string()),
+    }
+}
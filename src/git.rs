@@ -0,0 +1,146 @@
+use std::fmt;
+
+/// A git dependency such as `https://github.com/org/repo.git?path=/Pkg#v1.2`
+#[derive(Clone, PartialEq, Eq)]
+pub struct GitUrl {
+    pub repo: String,
+    /// Subfolder holding the package, from `?path=`
+    pub path: Option<String>,
+    /// Branch, tag or commit, from `#`
+    pub revision: Option<String>,
+}
+
+impl fmt::Display for GitUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.repo)?;
+        if let Some(path) = &self.path {
+            write!(f, "?path={}", path)?;
+        }
+        if let Some(revision) = &self.revision {
+            write!(f, "#{}", revision)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether a manifest version points to a git repository rather than the registry or a local folder.
+pub fn is_git(version: &str) -> bool {
+    if let Some(path) = version.strip_prefix("file:") {
+        // local tarballs and folders, unless it is a `file://` url to a repository
+        let repo = path.split(['?', '#']).next().unwrap_or(path);
+        return path.starts_with("//") && repo.ends_with(".git");
+    }
+
+    version.starts_with("git+") || version.starts_with("git@") || version.contains("://")
+}
+
+/// Parses a git dependency the way the Package Manager reads it.
+pub fn parse(version: &str) -> Result<GitUrl, String> {
+    let (rest, revision) = match version.split_once('#') {
+        Some((_, "")) => return Err(format!("`{}` has an empty revision after `#`", version)),
+        Some((rest, revision)) => (rest, Some(revision.to_string())),
+        None => (version, None),
+    };
+
+    let (repo, path) = match rest.split_once("?path=") {
+        Some((_, "")) => return Err(format!("`{}` has an empty `?path=`", version)),
+        Some((repo, path)) => (repo, Some(path.to_string())),
+        None => (rest, None),
+    };
+
+    if repo.contains('?') {
+        return Err(format!("`{}` has a query other than `?path=`", version));
+    }
+
+    let url = repo.strip_prefix("git+").unwrap_or(repo);
+    if let Some(scp) = url.strip_prefix("git@") {
+        // git@github.com:org/repo.git
+        match scp.split_once(':') {
+            Some((host, path)) if !host.is_empty() && !path.is_empty() => {}
+            _ => return Err(format!("`{}` is not a valid ssh git url", version)),
+        }
+    } else {
+        let (scheme, location) = url
+            .split_once("://")
+            .ok_or_else(|| format!("`{}` is not a git url", version))?;
+
+        if !["https", "http", "ssh", "git", "file"].contains(&scheme) {
+            return Err(format!(
+                "`{}` uses an unsupported scheme `{}`",
+                version, scheme
+            ));
+        }
+
+        if location.is_empty() || (scheme != "file" && !location.contains('/')) {
+            return Err(format!("`{}` has no repository path", version));
+        }
+
+        // the Package Manager only treats plain http urls as git when they end in .git
+        if (scheme == "https" || scheme == "http") && repo == url && !url.ends_with(".git") {
+            return Err(format!(
+                "`{}` must end in `.git` or start with `git+` to be fetched with git",
+                version
+            ));
+        }
+    }
+
+    Ok(GitUrl {
+        repo: repo.to_string(),
+        path,
+        revision,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_git() {
+        assert!(is_git("https://github.com/org/repo.git"));
+        assert!(is_git("git+https://example.com/org/repo"));
+        assert!(is_git("git@github.com:org/repo.git"));
+        assert!(is_git("file:///repos/repo.git#v2"));
+
+        assert!(!is_git("1.2.3"));
+        assert!(!is_git("file:../local"));
+        assert!(!is_git("file:com.acme.tools-1.0.0.tgz"));
+    }
+
+    #[test]
+    fn parses_parts() {
+        let url = parse("https://github.com/org/repo.git?path=/Packages/Tools#v1.2").unwrap();
+        assert_eq!(url.repo, "https://github.com/org/repo.git");
+        assert_eq!(url.path.as_deref(), Some("/Packages/Tools"));
+        assert_eq!(url.revision.as_deref(), Some("v1.2"));
+        assert_eq!(
+            url.to_string(),
+            "https://github.com/org/repo.git?path=/Packages/Tools#v1.2"
+        );
+
+        let url = parse("git@github.com:org/repo.git").unwrap();
+        assert_eq!(url.repo, "git@github.com:org/repo.git");
+        assert_eq!(url.path, None);
+        assert_eq!(url.revision, None);
+
+        assert!(parse("git+https://example.com/org/repo#main").is_ok());
+        assert!(parse("ssh://git@example.com/org/repo.git").is_ok());
+        assert!(parse("file:///repos/repo.git#v2").is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid() {
+        // empty revision and path
+        assert!(parse("https://github.com/org/repo.git#").is_err());
+        assert!(parse("https://github.com/org/repo.git?path=").is_err());
+        // other queries
+        assert!(parse("https://github.com/org/repo.git?ref=main").is_err());
+        // plain https needs `.git` or `git+`
+        assert!(parse("https://github.com/org/repo#v1").is_err());
+        assert!(parse("ftp://example.com/repo.git").is_err());
+        assert!(parse("git@github.com").is_err());
+        assert!(parse("https://example.com").is_err());
+        assert!(parse("1.2.3").is_err());
+    }
+}
@@ -0,0 +1,96 @@
+use imagesize::ImageType;
+use std::{fs, path::Path};
+
+/// Larger images bloat every template archive the Hub loads
+const MAX_IMAGE_BYTES: u64 = 2 * 1024 * 1024;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// Large screenshot shown when the template is selected
+    Preview,
+    /// Small icon shown in the template list
+    Thumbnail,
+}
+
+impl Kind {
+    fn name(&self) -> &'static str {
+        match self {
+            Kind::Preview => "preview",
+            Kind::Thumbnail => "thumbnail",
+        }
+    }
+
+    /// Largest width and height the Hub displays without scaling down
+    fn max_size(&self) -> (usize, usize) {
+        match self {
+            Kind::Preview => (1920, 1080),
+            Kind::Thumbnail => (512, 512),
+        }
+    }
+}
+
+/// An image to copy into the package
+pub struct Image {
+    pub kind: Kind,
+    pub source: String,
+    /// Path inside `package/`, as written to package.json
+    pub target: String,
+}
+
+/// Checks that `source` is a PNG or JPEG of a sensible size, and picks where it goes in the package.
+pub fn load(source: &str, kind: Kind) -> Result<Image, String> {
+    let path = Path::new(source);
+    let metadata = fs::metadata(path).map_err(|e| format!("could not read `{}`: {}", source, e))?;
+
+    if metadata.len() > MAX_IMAGE_BYTES {
+        return Err(format!(
+            "{} image `{}` is {} bytes, the limit is {}",
+            kind.name(),
+            source,
+            metadata.len(),
+            MAX_IMAGE_BYTES
+        ));
+    }
+
+    let header = fs::read(path).map_err(|e| format!("could not read `{}`: {}", source, e))?;
+    let extension = match imagesize::image_type(&header) {
+        Ok(ImageType::Png) => "png",
+        Ok(ImageType::Jpeg) => "jpg",
+        _ => {
+            return Err(format!(
+                "{} image `{}` must be a PNG or JPEG",
+                kind.name(),
+                source
+            ))
+        }
+    };
+
+    let size = imagesize::blob_size(&header)
+        .map_err(|e| format!("could not read the size of `{}`: {:?}", source, e))?;
+    let (max_width, max_height) = kind.max_size();
+
+    if size.width > max_width || size.height > max_height {
+        return Err(format!(
+            "{} image `{}` is {}x{}, the limit is {}x{}",
+            kind.name(),
+            source,
+            size.width,
+            size.height,
+            max_width,
+            max_height
+        ));
+    }
+
+    if kind == Kind::Thumbnail && size.width != size.height {
+        return Err(format!(
+            "thumbnail image `{}` is {}x{}, it must be square",
+            source, size.width, size.height
+        ));
+    }
+
+    Ok(Image {
+        kind,
+        source: source.to_string(),
+        target: format!("Documentation~/{}.{}", kind.name(), extension),
+    })
+}
@@ -0,0 +1,154 @@
+use crate::{archive, bundle};
+use indicatif::HumanBytes;
+use serde_derive::Serialize;
+use std::{io::Read, path::Path};
+
+#[derive(Serialize)]
+pub struct Inspection {
+    pub package: serde_json::Value,
+    pub dependencies: serde_json::Value,
+    pub default_scene: Option<String>,
+    pub default_scene_exists: bool,
+    pub has_bootstrap: bool,
+    pub files: Vec<FileEntry>,
+}
+
+#[derive(Serialize)]
+pub struct FileEntry {
+    pub path: String,
+    pub size: u64,
+}
+
+/// Reads a template `.tgz` without extracting it.
+pub fn inspect(path: &Path) -> std::io::Result<Inspection> {
+    let mut archive = archive::open_tgz(path)?;
+
+    let mut package = serde_json::Value::Null;
+    let mut dependencies = serde_json::Value::Null;
+    let mut files = Vec::new();
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = archive::package_path(&entry.path()?);
+        let size = entry.header().size()?;
+
+        match path.as_str() {
+            "package.json" => package = read_json(&mut entry)?,
+            "ProjectData~/Packages/manifest.json" => {
+                dependencies = read_json(&mut entry)?["dependencies"].take()
+            }
+            _ => {}
+        }
+
+        files.push(FileEntry { path, size });
+    }
+
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let default_scene = package["defaultScene"].as_str().map(|s| s.to_string());
+    let default_scene_exists = match &default_scene {
+        Some(scene) => {
+            let scene = format!("ProjectData~/{}", scene);
+            files.iter().any(|file| file.path == scene)
+        }
+        None => false,
+    };
+    let has_bootstrap = files.iter().any(|file| file.path == bundle::BOOTSTRAP_PATH);
+
+    Ok(Inspection {
+        package,
+        dependencies,
+        default_scene,
+        default_scene_exists,
+        has_bootstrap,
+        files,
+    })
+}
+
+impl Inspection {
+    pub fn print(&self) {
+        let field = |key: &str| self.package[key].as_str().unwrap_or("-").to_string();
+
+        println!();
+        println!("Package:");
+        println!("- Name: {}", field("name"));
+        println!("- Display name: {}", field("displayName"));
+        println!("- Version: {}", field("version"));
+        println!("- Unity: {} ({})", field("unity"), field("unityFull"));
+        println!("- Description: {}", field("description"));
+
+        println!();
+        println!("Dependencies:");
+        match self.dependencies.as_object() {
+            Some(dependencies) if !dependencies.is_empty() => {
+                for (name, version) in dependencies {
+                    println!("- {}: {}", name, version.as_str().unwrap_or("?"));
+                }
+            }
+            _ => println!("- none"),
+        }
+
+        println!();
+        println!("Default scene:");
+        match &self.default_scene {
+            Some(scene) if self.default_scene_exists => println!("- {}", scene),
+            Some(scene) => println!("- {} (missing from ProjectData~)", scene),
+            None => println!("- none"),
+        }
+
+        println!();
+        println!(
+            "Manifest override script: {}",
+            if self.has_bootstrap {
+                "present"
+            } else {
+                "missing"
+            }
+        );
+
+        println!();
+        println!("Files:");
+        let mut current: Vec<&str> = Vec::new();
+        for file in &self.files {
+            let parts: Vec<&str> = file.path.split('/').collect();
+            let (name, folders) = parts.split_last().unwrap();
+
+            // print folders as they are entered
+            let shared = current
+                .iter()
+                .zip(folders.iter())
+                .take_while(|(a, b)| a == b)
+                .count();
+            for (depth, folder) in folders.iter().enumerate().skip(shared) {
+                println!("{}{}/", "  ".repeat(depth), folder);
+            }
+            current = folders.to_vec();
+
+            println!(
+                "{}{} ({})",
+                "  ".repeat(folders.len()),
+                name,
+                HumanBytes(file.size)
+            );
+        }
+
+        println!();
+        println!(
+            "{} files, {}",
+            self.files.len(),
+            HumanBytes(self.files.iter().map(|file| file.size).sum())
+        );
+        println!();
+    }
+}
+
+fn read_json(reader: &mut impl Read) -> std::io::Result<serde_json::Value> {
+    let mut contents = String::new();
+    reader.read_to_string(&mut contents)?;
+
+    Ok(serde_json::from_str(&contents)?)
+}
@@ -0,0 +1,74 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use sha1::Sha1;
+use sha2::{Digest as _, Sha256, Sha512};
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    path::Path,
+};
+
+pub struct Digest {
+    /// Lowercase hex SHA-256, as written to the `.sha256` sidecar
+    pub sha256: String,
+    /// npm-style subresource integrity string (`sha512-<base64>`)
+    pub integrity: String,
+    /// Lowercase hex SHA-1, the `shasum` older npm clients check
+    pub shasum: String,
+}
+
+pub fn digest_file(path: &Path) -> io::Result<Digest> {
+    let mut file = File::open(path)?;
+    let mut sha1 = Sha1::new();
+    let mut sha256 = Sha256::new();
+    let mut sha512 = Sha512::new();
+    let mut buffer = [0u8; 64 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+
+        sha1.update(&buffer[..read]);
+        sha256.update(&buffer[..read]);
+        sha512.update(&buffer[..read]);
+    }
+
+    Ok(Digest {
+        sha256: hex(&sha256.finalize()),
+        integrity: format!("sha512-{}", STANDARD.encode(sha512.finalize())),
+        shasum: hex(&sha1.finalize()),
+    })
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+pub fn sidecar_path(path: &Path) -> String {
+    format!("{}.sha256", path.to_str().unwrap())
+}
+
+/// Writes `<path>.sha256` in the same format `sha256sum` uses.
+pub fn write_sidecar(path: &Path, digest: &Digest) -> io::Result<String> {
+    let sidecar = sidecar_path(path);
+    let file_name = path.file_name().unwrap().to_str().unwrap();
+    fs::write(&sidecar, format!("{}  {}\n", digest.sha256, file_name))?;
+
+    Ok(sidecar)
+}
+
+/// Reads the expected hash back out of a `.sha256` sidecar.
+pub fn read_sidecar(path: &Path) -> io::Result<String> {
+    let contents = fs::read_to_string(sidecar_path(path))?;
+    let hash = contents.split_whitespace().next().unwrap_or_default();
+
+    if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("`{}` is not a valid sha256 sidecar", sidecar_path(path)),
+        ));
+    }
+
+    Ok(hash.to_lowercase())
+}
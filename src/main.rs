@@ -1,15 +1,36 @@
+mod archive;
+mod bump;
 mod bundle;
 mod config;
+mod date;
+mod deps;
+mod documents;
+mod git;
+mod images;
+mod inspect;
+mod integrity;
+mod pack;
+mod publish;
+mod validate;
+mod vendor;
 
 use std::{
-    fs::{DirEntry, File},
+    collections::{BTreeMap, BTreeSet},
+    fs::DirEntry,
+    path::{Path, PathBuf},
     process::exit,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+    time::Instant,
 };
 
 use clap::{Parser, Subcommand};
-use console::Term;
-use dialoguer::{theme::ColorfulTheme, Input, MultiSelect, Select};
-use flate2::{write::GzEncoder, Compression};
+use console::{style, Term};
+use dialoguer::{theme::ColorfulTheme, FuzzySelect, Input, MultiSelect, Select};
+use indicatif::{HumanBytes, MultiProgress, ProgressBar, ProgressStyle};
 use serde_json::{json, Map};
 use std::fs;
 
@@ -27,27 +48,191 @@ enum BasicCommands {
 
     /// Packs a unity template from a generated build
     Pack(PackCommand),
+
+    /// Verifies a packed template against its .sha256 file
+    Verify(VerifyCommand),
+
+    /// Shows what is inside a template .tgz
+    Inspect(InspectCommand),
+
+    /// Extracts a template .tgz back into an editable build
+    Unpack(UnpackCommand),
+
+    /// Checks a build against the Unity Hub template requirements
+    Validate(ValidateCommand),
+
+    /// Bumps the version of a build
+    Bump(BumpCommand),
+
+    /// Copies a build to another Unity version, remapping its dependencies
+    Retarget(RetargetCommand),
+
+    /// Copies registry packages into a build so new projects open without the registry
+    Vendor(VendorCommand),
+
+    /// Uploads a packed template to an npm-compatible registry
+    Publish(PublishCommand),
 }
 
 #[derive(Debug, clap::Args)]
 pub struct NewCommand {
     pub name: String,
+
+    /// Starts from an installed template, by package name or .tgz path
+    #[arg(long)]
+    pub from_template: Option<String>,
+
+    /// Preview image shown when the template is selected, overrides the config
+    #[arg(long)]
+    pub preview: Option<String>,
+
+    /// Square icon shown in the template list, overrides the config
+    #[arg(long)]
+    pub thumbnail: Option<String>,
+
+    /// Which version to use when a package is in the project and bundled with the editor
+    #[arg(long, value_enum, default_value_t)]
+    pub dependency_policy: deps::Policy,
+
+    /// Dependency presets from the config to pre-select, prompts for them when empty
+    #[arg(long)]
+    pub preset: Vec<String>,
+
+    /// Pins the project's dependencies to the versions in its packages-lock.json
+    #[arg(long)]
+    pub lock: bool,
 }
 
 #[derive(Debug, clap::Args)]
 pub struct PackCommand {
-    // pub name: String,
+    /// Build folders to pack, prompts for them when empty
+    pub builds: Vec<String>,
+
+    /// Timestamp for every archive entry, defaults to `SOURCE_DATE_EPOCH`
+    #[arg(long)]
+    pub mtime: Option<u64>,
+
+    /// Compression level, from 0 (fastest) to 9 (smallest)
+    #[arg(long, default_value_t = 6, value_parser = clap::value_parser!(u32).range(0..=9))]
+    pub compression_level: u32,
+
+    /// Archive format, only `tgz` can be loaded by the Unity Hub
+    #[arg(long, value_enum, default_value_t = pack::Format::Tgz)]
+    pub format: pack::Format,
+
+    /// Packs even if validation reports errors
+    #[arg(long)]
+    pub skip_validation: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct VerifyCommand {
+    pub file: String,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct InspectCommand {
+    pub file: String,
+
+    /// Prints the result as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct UnpackCommand {
+    pub file: String,
+
+    /// Replaces the build folder if it already exists
+    #[arg(long)]
+    pub force: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ValidateCommand {
+    /// Build folder to check, prompts for it when empty
+    pub build: Option<String>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct BumpCommand {
+    pub build: String,
+
+    #[arg(value_enum)]
+    pub part: bump::Part,
+
+    /// Prerelease identifier, such as `beta` for `1.0.0-beta.0`
+    #[arg(long)]
+    pub preid: Option<String>,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct RetargetCommand {
+    pub build: String,
+
+    /// Installed editor to target, such as `2022.3.5f1`, or `2022.3` if only one is installed
+    #[arg(long)]
+    pub unity: String,
+
+    /// Removes registry packages the new editor doesn't bundle, instead of keeping their version
+    #[arg(long)]
+    pub remove_unbundled: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct VendorCommand {
+    pub build: String,
+
+    /// Packages to vendor, defaults to every dependency the editor does not bundle
+    pub packages: Vec<String>,
+
+    /// Folder with `<name>-<version>.tgz` files or extracted packages, can be repeated
+    #[arg(long)]
+    pub from: Vec<String>,
+
+    /// Unity project whose `Library/PackageCache` has the packages
+    #[arg(long)]
+    pub project: Option<String>,
+
+    /// Copies packages in as embedded folders instead of `file:` tarballs
+    #[arg(long)]
+    pub embed: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct PublishCommand {
+    pub file: String,
+
+    /// Registry url, overrides the config
+    #[arg(long)]
+    pub registry: Option<String>,
+
+    /// Dist-tag to point at the published version
+    #[arg(long, default_value = "latest")]
+    pub tag: String,
 }
 
 fn main() {
     let config = config::load_config();
 
-    println!("[create-unity-template - Created by Andrew Burke]");
     let args = Args::parse();
 
+    // keep stdout clean for machine-readable output
+    if !matches!(&args.basic_commands, BasicCommands::Inspect(cmd) if cmd.json) {
+        println!("[create-unity-template - Created by Andrew Burke]");
+    }
+
     match args.basic_commands {
         BasicCommands::New(cmd) => create_project(config, cmd).unwrap(),
         BasicCommands::Pack(cmd) => pack_project(config, cmd).unwrap(),
+        BasicCommands::Verify(cmd) => verify_project(cmd).unwrap(),
+        BasicCommands::Inspect(cmd) => inspect_project(cmd).unwrap(),
+        BasicCommands::Unpack(cmd) => unpack_project(cmd).unwrap(),
+        BasicCommands::Validate(cmd) => validate_project(config, cmd).unwrap(),
+        BasicCommands::Bump(cmd) => bump_project(cmd).unwrap(),
+        BasicCommands::Retarget(cmd) => retarget_project(config, cmd).unwrap(),
+        BasicCommands::Vendor(cmd) => vendor_project(config, cmd).unwrap(),
+        BasicCommands::Publish(cmd) => publish_project(config, cmd).unwrap(),
     };
 }
 
@@ -65,8 +250,63 @@ fn create_project(config: config::Config, cmd: NewCommand) -> std::io::Result<()
     // defaultScene
     // repository
 
+    // forked templates pre-fill the prompts from their package.json
+    let template = cmd
+        .from_template
+        .as_ref()
+        .map(|from| fork_template(&config, &versions, from))
+        .transpose()?;
+    let forked = template.as_ref().map(|(_, package)| package);
+
     let mut name = cmd.name.to_lowercase().replace("-", "");
     name.retain(|c| !c.is_whitespace());
+    let name = format!("{}{}", config.essentials.package_prefix, name);
+
+    if let Err(e) = bundle::validate_package_prefix(&config.essentials.package_prefix) {
+        eprintln!("Warning: {}", e);
+    }
+
+    if let Err(e) = bundle::validate_package_name(&name) {
+        eprintln!("Invalid template name `{}`", cmd.name);
+        eprintln!("> {}", e);
+        exit(1);
+    }
+
+    if let Err(e) = deps::expand_presets(&config.presets, &cmd.preset) {
+        eprintln!("Invalid presets");
+        eprintln!("> {}", e);
+        exit(1);
+    }
+
+    // images, checked before any prompts
+    let images: Vec<images::Image> = [
+        (
+            cmd.preview.as_ref(),
+            &config.images.preview,
+            images::Kind::Preview,
+        ),
+        (
+            cmd.thumbnail.as_ref(),
+            &config.images.thumbnail,
+            images::Kind::Thumbnail,
+        ),
+    ]
+    .into_iter()
+    .filter_map(|(flag, configured, kind)| flag.or(configured.as_ref()).map(|path| (path, kind)))
+    .map(|(path, kind)| {
+        images::load(path, kind).unwrap_or_else(|e| {
+            eprintln!("Invalid image");
+            eprintln!("> {}", e);
+            exit(1);
+        })
+    })
+    .collect();
+    let image_target = |kind: images::Kind| {
+        images
+            .iter()
+            .find(|image| image.kind == kind)
+            .map(|image| image.target.clone())
+    };
 
     // displayName
     // QOL parse the name as the display name
@@ -91,14 +331,23 @@ fn create_project(config: config::Config, cmd: NewCommand) -> std::io::Result<()
     // description
     let description = Input::<String>::new()
         .with_prompt("Description")
-        .with_initial_text("")
+        .with_initial_text(
+            forked
+                .map(|package| package.description.as_deref().unwrap_or_default())
+                .unwrap_or(""),
+        )
         .allow_empty(true)
         .interact_text()?;
 
     // keywords
     let keywords = Input::<String>::new()
         .with_prompt("Keywords")
-        .with_initial_text("")
+        .with_initial_text(
+            forked
+                .and_then(|package| package.keywords.as_ref())
+                .map(|keywords| keywords.join(", "))
+                .unwrap_or_default(),
+        )
         .allow_empty(true)
         .interact_text()?;
 
@@ -106,24 +355,43 @@ fn create_project(config: config::Config, cmd: NewCommand) -> std::io::Result<()
     let items = vec!["2D", "3D"];
     let category = Select::with_theme(&ColorfulTheme::default())
         .items(&items)
-        .default(0)
+        .default(
+            items
+                .iter()
+                .position(|item| {
+                    Some(*item) == forked.and_then(|package| package.category.as_deref())
+                })
+                .unwrap_or(0),
+        )
         .with_prompt("Category")
         .interact_on_opt(&Term::stderr())?;
 
-    let default_scene = Input::<String>::new()
-        .with_prompt("Default Scene")
-        .with_initial_text("Assets/Scenes/SampleScene.unity")
+    let repository = Input::<String>::new()
+        .with_prompt("Repository")
+        .with_initial_text(
+            forked
+                .and_then(|package| package.repository.as_ref())
+                .and_then(|repository| repository.url())
+                .unwrap_or(""),
+        )
+        .allow_empty(true)
+        .interact_text()?;
+
+    // author
+    let author = Input::<String>::new()
+        .with_prompt("Author")
+        .with_initial_text(config.documents.license_holder.clone().unwrap_or_default())
         .allow_empty(true)
         .interact_text()?;
 
-    let repository = Input::<String>::new()
-        .with_prompt("Repository")
-        .with_initial_text("")
+    // documentationUrl
+    let documentation_url = Input::<String>::new()
+        .with_prompt("Documentation URL")
         .allow_empty(true)
         .interact_text()?;
 
     let category = match category {
-        Some(index) => items[index].clone(),
+        Some(index) => items[index],
         None => {
             eprintln!("Did not select a category.");
             exit(1);
@@ -131,20 +399,31 @@ fn create_project(config: config::Config, cmd: NewCommand) -> std::io::Result<()
     };
 
     // unity
+    // one build is made per selected editor
     let items: Vec<String> = versions
         .iter()
         .map(|version| format!("{}.{}", version.major, version.minor))
         .collect();
+    let default_index = items
+        .iter()
+        .position(|item| Some(item.as_str()) == forked.map(|package| package.unity_full()))
+        .unwrap_or(0);
+    let items_defaults: Vec<bool> = (0..items.len())
+        .map(|index| index == default_index)
+        .collect();
 
-    let unity = Select::with_theme(&ColorfulTheme::default())
+    let editors = MultiSelect::with_theme(&ColorfulTheme::default())
         .items(&items)
-        .default(0)
-        .with_prompt("Unity version")
+        .with_prompt("Unity versions")
+        .defaults(&items_defaults)
         .interact_on_opt(&Term::stderr())?;
 
-    let unity = match unity {
-        Some(index) => versions[index].clone(),
-        None => {
+    let editors: Vec<config::Version> = match editors {
+        Some(indices) if !indices.is_empty() => indices
+            .into_iter()
+            .map(|index| versions[index].clone())
+            .collect(),
+        _ => {
             eprintln!("Did not select a version.");
             exit(1);
         }
@@ -153,91 +432,587 @@ fn create_project(config: config::Config, cmd: NewCommand) -> std::io::Result<()
     // version
     let version = Input::<String>::new()
         .with_prompt("Package version")
-        .with_initial_text("0.0.1")
+        .with_initial_text(
+            forked
+                .map(|package| package.version.as_str())
+                .unwrap_or("0.0.1"),
+        )
+        .validate_with(|input: &String| match semver::Version::parse(input) {
+            Ok(_) => Ok(()),
+            Err(e) => Err(format!("Not a valid semver version: {}", e)),
+        })
         .interact_text()?;
 
     // projectPath
-    let project_path = Input::<String>::new()
-        .with_prompt("Project path")
-        .interact_text()?;
+    let project_path = match &template {
+        Some((project_dir, _)) => project_dir.path().to_str().unwrap().to_string(),
+        None => Input::<String>::new()
+            .with_prompt("Project path")
+            .interact_text()?,
+    };
 
-    // dependencies
-    let mut project_deps = config::load_dependencies_from(&project_path);
-    let mut built_in_deps = config::load_dependencies(&config, &unity);
-    built_in_deps.append(&mut project_deps);
-    built_in_deps.sort();
-    built_in_deps.dedup_by(|a, b| a.name.eq(&b.name));
+    // defaultScene
+    // only scenes that exist in the project can be picked, typing filters them
+    let scenes = bundle::find_scenes(Path::new(&project_path));
+    let default_scene = if scenes.is_empty() {
+        println!("No scenes found in the project, leaving the default scene empty.");
+        String::new()
+    } else {
+        let mut items = scenes.clone();
+        items.push("(none)".to_string());
+
+        let initial = forked
+            .and_then(|package| package.default_scene.as_deref())
+            .unwrap_or("Assets/Scenes/SampleScene.unity");
+        let default_scene = FuzzySelect::with_theme(&ColorfulTheme::default())
+            .items(&items)
+            .default(
+                scenes
+                    .iter()
+                    .position(|scene| *scene == initial)
+                    .unwrap_or(0),
+            )
+            .with_prompt("Default Scene")
+            .interact_on_opt(&Term::stderr())?;
+
+        match default_scene {
+            Some(index) if index < scenes.len() => scenes[index].clone(),
+            Some(_) => String::new(),
+            None => {
+                eprintln!("Did not select a default scene.");
+                exit(1);
+            }
+        }
+    };
 
-    let items: Vec<String> = built_in_deps
+    // dependencies
+    let project_deps = deps::project_packages(&project_path);
+    let editor_deps: Vec<deps::Packages> = editors
         .iter()
-        .map(|dep| dep.name.clone().unwrap())
+        .map(|editor| deps::editor_packages(&config, editor))
         .collect();
-    let items_defaults: Vec<bool> = items
-        .iter()
-        .map(|item| config.essentials.default_dependencies.contains(item))
+
+    // presets
+    let presets: Vec<String> = if !cmd.preset.is_empty() || config.presets.is_empty() {
+        cmd.preset.clone()
+    } else {
+        let items: Vec<&String> = config.presets.keys().collect();
+        let presets = MultiSelect::with_theme(&ColorfulTheme::default())
+            .items(&items)
+            .with_prompt("Presets")
+            .interact_on_opt(&Term::stderr())?;
+
+        presets
+            .unwrap_or_default()
+            .into_iter()
+            .map(|index| items[index].clone())
+            .collect()
+    };
+
+    let (preset_packages, preset_pins) = match deps::expand_presets(&config.presets, &presets) {
+        Ok(expanded) => expanded,
+        Err(e) => {
+            eprintln!("Invalid presets");
+            eprintln!("> {}", e);
+            exit(1);
+        }
+    };
+
+    // the lock file pins what the team tested, pins set in presets still win
+    let mut pins = if cmd.lock {
+        match deps::locked_packages(&project_path) {
+            Ok(locked) => locked,
+            Err(e) => {
+                eprintln!("Could not use the project's lock file");
+                eprintln!("> {}", e);
+                exit(1);
+            }
+        }
+    } else {
+        deps::Packages::new()
+    };
+    pins.extend(preset_pins);
+
+    let mut names: Vec<String> = project_deps
+        .keys()
+        .chain(editor_deps.iter().flat_map(|packages| packages.keys()))
+        .chain(pins.keys())
+        .cloned()
+        .collect::<BTreeSet<String>>()
+        .into_iter()
         .collect();
+    // modules get their own picker below
+    names.retain(|name| !deps::is_module(name));
+    // the Package Manager would reject these
+    names.retain(|name| match project_deps.get(name) {
+        Some(version) if git::is_git(version) => match git::parse(version) {
+            Ok(_) => true,
+            Err(e) => {
+                eprintln!("Warning: skipping `{}`, {}", name, e);
+                false
+            }
+        },
+        _ => true,
+    });
+    // the sort is stable, so names stay alphabetical within each group
+    names.sort_by_key(|name| deps::group(name, &project_deps));
 
-    let dependencies = MultiSelect::with_theme(&ColorfulTheme::default())
-        .items(&items)
-        .with_prompt("Dependencies")
-        .defaults(&items_defaults)
-        .max_length(10)
-        .interact_on_opt(&Term::stderr())?;
+    let display_names = deps::display_names(
+        &config,
+        &editors,
+        &editor_deps,
+        &project_path,
+        &project_deps,
+    );
+
+    // show the version each editor will get, and flag packages some editors can't provide
+    let items: Vec<String> = names
+        .iter()
+        .map(|name| {
+            let resolved: Vec<(String, deps::Resolved)> = editors
+                .iter()
+                .zip(&editor_deps)
+                .filter_map(|(editor, packages)| {
+                    let package =
+                        deps::resolve(name, &project_deps, packages, &pins, cmd.dependency_policy)?;
+                    Some((format!("{}.{}", editor.major, editor.minor), package))
+                })
+                .collect();
+            let missing: Vec<String> = editors
+                .iter()
+                .map(|editor| format!("{}.{}", editor.major, editor.minor))
+                .filter(|editor| resolved.iter().all(|(resolved, _)| resolved != editor))
+                .collect();
 
-    let dependencies = {
-        let mut buffer = Vec::new();
+            let versions = if resolved.windows(2).all(|pair| pair[0].1 == pair[1].1) {
+                let (_, package) = &resolved[0];
+                format!("{} ({})", package.version, package.source)
+            } else {
+                resolved
+                    .iter()
+                    .map(|(editor, package)| {
+                        format!("{} ({}) on {}", package.version, package.source, editor)
+                    })
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            };
 
-        if let Some(indices) = dependencies {
-            for index in indices {
-                buffer.push(built_in_deps.get(index));
+            let group = deps::group(name, &project_deps);
+            let mut item = match display_names.get(name) {
+                Some(display_name) => {
+                    format!("{} | {} - {} {}", group, display_name, name, versions)
+                }
+                None => format!("{} | {} {}", group, name, versions),
+            };
+
+            if !missing.is_empty() {
+                item.push_str(&format!(" (not in {})", missing.join(", ")));
             }
+
+            item
+        })
+        .collect();
+    for package in &preset_packages {
+        if !names.contains(package) && !deps::is_module(package) {
+            eprintln!(
+                "Warning: preset package `{}` is not in the project or the selected editors, skipping it",
+                package
+            );
         }
+    }
 
-        buffer
+    // presets replace the config's default dependencies
+    let preselected = if presets.is_empty() {
+        &config.essentials.default_dependencies
+    } else {
+        &preset_packages
     };
+    let items_defaults: Vec<bool> = names
+        .iter()
+        .map(|name| preselected.contains(name))
+        .collect();
 
-    let dependencies = {
-        let mut map: Map<String, serde_json::Value> = Map::new();
+    let dependencies: Vec<String> = select_many(
+        "Dependencies (type to search, pick to toggle)",
+        &items,
+        items_defaults,
+    )?
+    .into_iter()
+    .map(|index| names[index].clone())
+    .collect();
+    println!("Dependencies: {}", dependencies.join(", "));
 
-        for dep in dependencies.iter().map(|dep| dep.unwrap()) {
-            let version = dep.version.clone().unwrap().to_string();
-            map.insert(
-                dep.name.clone().unwrap(),
-                serde_json::Value::String(version[1..(version.len() - 1)].to_string()),
-            );
+    // git revisions can be changed per template
+    for name in &dependencies {
+        let Some(version) = pins.get(name).or_else(|| project_deps.get(name)) else {
+            continue;
+        };
+        if !git::is_git(version) {
+            continue;
         }
+        let Ok(url) = git::parse(version) else {
+            continue;
+        };
+
+        let revision = Input::<String>::new()
+            .with_prompt(format!(
+                "Revision for {} (empty for the default branch)",
+                name
+            ))
+            .with_initial_text(url.revision.clone().unwrap_or_default())
+            .allow_empty(true)
+            .interact_text()?;
+
+        let url = git::GitUrl {
+            revision: (!revision.is_empty()).then_some(revision),
+            ..url
+        };
+        pins.insert(name.clone(), url.to_string());
+    }
 
-        map
+    // modules
+    let editor_modules: Vec<BTreeMap<String, Option<String>>> = editors
+        .iter()
+        .map(|editor| deps::editor_modules(&config, editor))
+        .collect();
+    let project_modules: Vec<&String> = project_deps
+        .keys()
+        .filter(|name| deps::is_module(name))
+        .collect();
+
+    let module_names: Vec<String> = editor_modules
+        .iter()
+        .flat_map(|modules| modules.keys())
+        .chain(project_modules.iter().copied())
+        .cloned()
+        .collect::<BTreeSet<String>>()
+        .into_iter()
+        .collect();
+
+    let modules: Vec<String> = if module_names.is_empty() {
+        Vec::new()
+    } else {
+        let items = ["all", "minimal", "project"];
+        let preset = Select::with_theme(&ColorfulTheme::default())
+            .items(&items)
+            .default(if project_modules.is_empty() { 0 } else { 2 })
+            .with_prompt("Modules")
+            .interact_on_opt(&Term::stderr())?;
+
+        let items_defaults: Vec<bool> = module_names
+            .iter()
+            .map(|name| {
+                preset_packages.contains(name)
+                    || match preset.map(|index| items[index]) {
+                        Some("all") => true,
+                        Some("minimal") => deps::MINIMAL_MODULES.contains(&name.as_str()),
+                        Some("project") => project_modules.contains(&name),
+                        _ => false,
+                    }
+            })
+            .collect();
+
+        let items: Vec<String> = module_names
+            .iter()
+            .map(|name| {
+                let display_name = editor_modules
+                    .iter()
+                    .find_map(|modules| modules.get(name).cloned().flatten());
+                let missing: Vec<String> = editors
+                    .iter()
+                    .zip(&editor_modules)
+                    .filter(|(_, modules)| !modules.is_empty() && !modules.contains_key(name))
+                    .map(|(editor, _)| format!("{}.{}", editor.major, editor.minor))
+                    .collect();
+
+                let mut item = match display_name {
+                    Some(display_name) => format!("{} - {}", display_name, name),
+                    None => name.clone(),
+                };
+                if !missing.is_empty() {
+                    item.push_str(&format!(" (not in {})", missing.join(", ")));
+                }
+
+                item
+            })
+            .collect();
+
+        select_many(
+            "Modules (type to search, pick to toggle)",
+            &items,
+            items_defaults,
+        )?
+        .into_iter()
+        .map(|index| module_names[index].clone())
+        .collect()
     };
+    println!("Modules: {} selected", modules.len());
 
-    let data = bundle::Data {
+    let mut data = bundle::Data {
         name,
-        display_name,
+        display_name: Some(display_name),
         version,
-        unity: unity.major.clone(),
-        unity_full: format!("{}.{}", unity.major, unity.minor),
-        keywords: keywords
-            .split(',')
-            .map(|key| key.trim().to_string())
-            .filter(|key| !key.is_empty())
-            .collect(),
-        category: category.to_string(),
-        description,
-        dependencies: json!(dependencies),
-        default_scene,
-        repository,
+        kind: Some("template".to_string()),
+        host: Some("hub".to_string()),
+        unity: None,
+        unity_full: None,
+        keywords: Some(
+            keywords
+                .split(',')
+                .map(|key| key.trim().to_string())
+                .filter(|key| !key.is_empty())
+                .collect(),
+        ),
+        category: Some(category.to_string()),
+        description: Some(description),
+        author: (!author.is_empty()).then_some(bundle::Author::Name(author)),
+        license: None,
+        dependencies: None,
+        default_scene: (!default_scene.is_empty()).then_some(default_scene),
+        repository: (!repository.is_empty()).then_some(bundle::Repository::Url(repository)),
+        documentation_url: (!documentation_url.is_empty()).then_some(documentation_url),
+        changelog_url: None,
+        licenses_url: None,
+        preview_image: image_target(images::Kind::Preview),
+        thumbnail: image_target(images::Kind::Thumbnail),
+        hide_in_editor: None,
+        samples: None,
+        upm_ci: None,
+        upm: None,
+        extra: Map::new(),
+    };
+
+    // license
+    let mut items: Vec<String> = documents::LICENSES
+        .iter()
+        .map(|id| id.to_string())
+        .collect();
+    if config.documents.license_template.is_some() {
+        items.push("custom".to_string());
+    }
+
+    let license = Select::with_theme(&ColorfulTheme::default())
+        .items(&items)
+        .default(
+            config
+                .documents
+                .license
+                .as_ref()
+                .and_then(|license| items.iter().position(|item| item == license))
+                .unwrap_or(0),
+        )
+        .with_prompt("License")
+        .interact_on_opt(&Term::stderr())?;
+
+    let license = match license {
+        Some(index) if items[index] == "custom" => {
+            // npm's convention for licenses without an SPDX id
+            data.license = Some("SEE LICENSE IN LICENSE.md".to_string());
+            documents::License::Custom
+        }
+        Some(index) => {
+            data.license = Some(items[index].clone());
+            documents::License::Spdx(items[index].clone())
+        }
+        None => {
+            eprintln!("Did not select a license.");
+            exit(1);
+        }
     };
 
-    bundle::build(&project_path, data);
+    for ((editor, editor_deps), editor_modules) in
+        editors.iter().zip(&editor_deps).zip(&editor_modules)
+    {
+        let unity_full = format!("{}.{}", editor.major, editor.minor);
+        let (mut resolved, mut missing) = deps::resolve_all(
+            &dependencies,
+            &project_deps,
+            editor_deps,
+            &pins,
+            cmd.dependency_policy,
+        );
+
+        // an editor without a BuiltInPackages listing is trusted to have them all
+        for module in &modules {
+            if editor_modules.is_empty() || editor_modules.contains_key(module) {
+                resolved.insert(module.clone(), deps::MODULE_VERSION.to_string());
+            } else {
+                missing.push(module.clone());
+            }
+        }
+
+        for name in missing {
+            match (resolved.get(&name), editor_deps.get(&name)) {
+                (Some(version), Some(bundled)) => eprintln!(
+                    "Warning: `{}` {} is not the version bundled with {} ({}), it comes from the registry",
+                    name, version, unity_full, bundled
+                ),
+                (Some(version), None) => eprintln!(
+                    "Warning: `{}` {} is not bundled with {}, it comes from the registry",
+                    name, version, unity_full
+                ),
+                (None, _) => eprintln!(
+                    "Warning: `{}` is not available for {}, leaving it out of that build",
+                    name, unity_full
+                ),
+            }
+        }
+
+        let mut data = data.clone();
+        data.unity = Some(editor.major.clone());
+        data.unity_full = Some(unity_full.clone());
+        data.dependencies = Some(json!(resolved));
+
+        let documents = match documents::render(&config, &data, &license) {
+            Ok(documents) => documents,
+            Err(e) => {
+                eprintln!("Could not generate the package documents");
+                eprintln!("> {}", e);
+                exit(1);
+            }
+        };
+
+        // builds for several editors need their own folders
+        let build_name = if editors.len() > 1 {
+            format!("{}-{}-{}", data.name, unity_full, data.version)
+        } else {
+            format!("{}-{}", data.name, data.version)
+        };
+
+        bundle::build(&project_path, &build_name, data, documents, &images);
+    }
 
     Ok(())
 }
 
-#[allow(unused_must_use)]
-fn pack_project(config: config::Config, _: PackCommand) -> std::io::Result<()> {
+/// A multi-select that can be filtered by typing, as dialoguer's fuzzy picker only picks one item.
+///
+/// Picking an item toggles it, and picking `Done` or pressing escape finishes.
+fn select_many(
+    prompt: &str,
+    items: &[String],
+    mut selected: Vec<bool>,
+) -> std::io::Result<Vec<usize>> {
+    let mut cursor = 0;
+
+    loop {
+        let rows: Vec<String> =
+            std::iter::once(format!(
+                "Done ({} selected)",
+                selected.iter().filter(|selected| **selected).count()
+            ))
+            .chain(items.iter().zip(&selected).map(|(item, selected)| {
+                format!("[{}] {}", if *selected { "x" } else { " " }, item)
+            }))
+            .collect();
+
+        let picked = FuzzySelect::with_theme(&ColorfulTheme::default())
+            .items(&rows)
+            .default(cursor)
+            .with_prompt(prompt)
+            .max_length(15)
+            .report(false)
+            .interact_on_opt(&Term::stderr())?;
+
+        match picked {
+            Some(0) | None => break,
+            Some(index) => {
+                selected[index - 1] = !selected[index - 1];
+                cursor = index;
+            }
+        }
+    }
+
+    Ok(selected
+        .iter()
+        .enumerate()
+        .filter(|(_, selected)| **selected)
+        .map(|(index, _)| index)
+        .collect())
+}
+
+/// Extracts the `ProjectData~` of an installed template into a temp folder to use as the source project.
+fn fork_template(
+    config: &config::Config,
+    versions: &[config::Version],
+    from: &str,
+) -> std::io::Result<(tempfile::TempDir, bundle::Data)> {
+    let tgz = if Path::new(from).is_file() {
+        PathBuf::from(from)
+    } else {
+        // accept both `com.unity.template.3d` and the short `3d`, as the Hub ships Unity's templates
+        let full_name = format!("{}{}", bundle::PACKAGE_PREFIX, from);
+        let templates: Vec<PathBuf> = versions
+            .iter()
+            .flat_map(|version| config::load_templates(config, version))
+            .filter(|path| {
+                let file_name = path.file_name().unwrap().to_str().unwrap();
+                file_name.starts_with(&format!("{}-", from))
+                    || file_name.starts_with(&format!("{}-", full_name))
+            })
+            .collect();
+
+        let items: Vec<String> = templates
+            .iter()
+            .map(|path| path.to_str().unwrap().to_string())
+            .collect();
+
+        match templates.len() {
+            0 => {
+                eprintln!("Could not find an installed template named `{}`", from);
+                exit(1);
+            }
+            1 => templates[0].clone(),
+            _ => {
+                let template = Select::with_theme(&ColorfulTheme::default())
+                    .items(&items)
+                    .default(0)
+                    .with_prompt("Template to fork")
+                    .interact_on_opt(&Term::stderr())?;
+
+                match template {
+                    Some(index) => templates[index].clone(),
+                    None => {
+                        eprintln!("Did not select a template.");
+                        exit(1);
+                    }
+                }
+            }
+        }
+    };
+
+    let package = match inspect::inspect(&tgz)
+        .and_then(|inspection| bundle::Data::from_value(inspection.package).map_err(Into::into))
+    {
+        Ok(package) => package,
+        Err(e) => {
+            eprintln!("Could not read template from `{}`", tgz.display());
+            eprintln!("> {}", e);
+            exit(1);
+        }
+    };
+
+    let project_dir = tempfile::tempdir()?;
+    archive::extract_tgz(&tgz, project_dir.path(), |path| {
+        if path == bundle::BOOTSTRAP_PATH {
+            return None;
+        }
+
+        path.strip_prefix("ProjectData~/")
+            .map(|path| path.to_string())
+    })?;
+    bundle::restore_manifest(project_dir.path())?;
+
+    println!("Forking `{}`", tgz.display());
+
+    Ok((project_dir, package))
+}
+
+/// Resolves build folder names, or prompts for them when none are given.
+fn select_builds(names: &[String], prompt: &str, multiple: bool) -> std::io::Result<Vec<PathBuf>> {
     // load up all builds in the folder in a list
     let build_path = ".\\builds";
-    let dir = fs::read_dir(&build_path)?;
+    let dir = fs::read_dir(build_path)?;
 
     let builds: Vec<DirEntry> = dir
         .filter(|path| path.as_ref().unwrap().path().is_dir())
@@ -248,107 +1023,630 @@ fn pack_project(config: config::Config, _: PackCommand) -> std::io::Result<()> {
         .iter()
         .map(|dir| dir.file_name().to_str().unwrap().to_string())
         .collect();
-    let project = Select::with_theme(&ColorfulTheme::default())
-        .items(&items)
-        .default(0)
-        .with_prompt("Project to build")
-        .interact_on_opt(&Term::stderr())?;
 
-    let project = match project {
-        Some(index) => &builds[index],
-        None => {
+    if !names.is_empty() {
+        return Ok(names
+            .iter()
+            .map(|name| match items.iter().position(|item| item == name) {
+                Some(index) => builds[index].path(),
+                None => {
+                    eprintln!("Could not find build `{}` in `{}`", name, build_path);
+                    exit(1);
+                }
+            })
+            .collect());
+    }
+
+    let indices = if multiple {
+        MultiSelect::with_theme(&ColorfulTheme::default())
+            .items(&items)
+            .with_prompt(prompt)
+            .interact_on_opt(&Term::stderr())?
+    } else {
+        Select::with_theme(&ColorfulTheme::default())
+            .items(&items)
+            .default(0)
+            .with_prompt(prompt)
+            .interact_on_opt(&Term::stderr())?
+            .map(|index| vec![index])
+    };
+
+    match indices {
+        Some(indices) if !indices.is_empty() => Ok(indices
+            .into_iter()
+            .map(|index| builds[index].path())
+            .collect()),
+        _ => {
             eprintln!("Did not select a project.");
             exit(1);
         }
-    };
+    }
+}
+
+#[allow(unused_must_use)]
+fn pack_project(config: config::Config, cmd: PackCommand) -> std::io::Result<()> {
+    let projects = select_builds(&cmd.builds, "Projects to build", true)?;
 
-    let project_name = project.file_name();
+    if !cmd.skip_validation {
+        let mut failed = false;
+
+        for project in &projects {
+            let report = validate::validate(&config, project);
+            if !report.issues.is_empty() {
+                report.print(project.file_name().unwrap().to_str().unwrap());
+            }
+            failed |= report.has_errors();
+        }
+
+        if failed {
+            eprintln!();
+            eprintln!("Validation failed, fix the errors above or pass --skip-validation.");
+            exit(1);
+        }
+    }
 
-    // package folder insides
-    let mut project = project.path().read_dir().unwrap();
     let output_path = ".\\outputs";
 
     fs::create_dir(output_path);
 
-    let path = format!("{}\\{}.tgz", output_path, project_name.to_str().unwrap());
-    let tar_gz = File::create(&path).unwrap();
-    let enc = GzEncoder::new(tar_gz, Compression::default());
+    let options = pack::Options {
+        format: cmd.format,
+        level: cmd.compression_level,
+        mtime: archive::resolve_mtime(cmd.mtime),
+    };
+    let progress = MultiProgress::new();
+    let style = ProgressStyle::with_template(
+        "{prefix:.bold} [{bar:30.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}) {msg}",
+    )
+    .unwrap()
+    .progress_chars("=> ");
+
+    let jobs: Vec<(PathBuf, ProgressBar)> = projects
+        .into_iter()
+        .map(|path| {
+            let bar = progress.add(ProgressBar::new(pack::input_size(&path).unwrap_or(0)));
+            bar.set_style(style.clone());
+            bar.set_prefix(path.file_name().unwrap().to_str().unwrap().to_string());
+            (path, bar)
+        })
+        .collect();
 
-    let mut tar = tar::Builder::new(enc);
-    let project_path = project.next().unwrap().unwrap().path();
-    let project_path = project_path.to_str().unwrap();
-    tar.append_dir_all("package", &project_path)
-        .unwrap_or_else(|e| panic!("Failed to pack tar file: {:?}", e));
+    // compress up to one build per core at a time
+    let workers = thread::available_parallelism()
+        .map(|count| count.get())
+        .unwrap_or(1)
+        .min(jobs.len());
+    let next_job = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::new());
+    let started = Instant::now();
 
-    // load package.json from project
-    let package_json_path = format!("{}\\package.json", &project_path);
-    println!("{}", package_json_path);
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let index = next_job.fetch_add(1, Ordering::SeqCst);
+                let Some((path, bar)) = jobs.get(index) else {
+                    break;
+                };
 
-    let contents = fs::read_to_string(&package_json_path).unwrap();
-    let data: serde_json::Value = serde_json::from_str(&contents).unwrap();
-    let version = &data.as_object().unwrap()["unityFull"].as_str().unwrap();
+                let result = pack::pack(path, output_path, &options, &mut |read| bar.inc(read));
+                match &result {
+                    Ok(_) => bar.finish_with_message("done"),
+                    Err(_) => bar.abandon_with_message("failed"),
+                }
 
-    let contents = r#"
-using System.IO;
-using UnityEditor;
-using UnityEngine;
+                results.lock().unwrap().push((index, result));
+            });
+        }
+    });
 
-public static class ___ManifestOverride
-{
-    private static readonly string InputPath = $"{Application.dataPath}/manifest.json";
+    let elapsed = started.elapsed();
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|(index, _)| *index);
 
-    [InitializeOnLoadMethod]
-    private static void OnLoad()
-    {
-        EditorApplication.delayCall += () => {
-            var fullScenePath = "FULL_SCENE_PATH";
-            if (!string.IsNullOrEmpty(fullScenePath)) {
-                Debug.LogWarning($"Attempting to open: {fullScenePath}");
-                UnityEditor.SceneManagement.EditorSceneManager.OpenScene(fullScenePath);
+    let total_bytes: u64 = jobs.iter().map(|(_, bar)| bar.position()).sum();
+    let throughput = total_bytes as f64 / elapsed.as_secs_f64().max(f64::EPSILON);
+
+    println!();
+    println!(
+        "Packed {} of {} template(s), {} in {:.2}s ({}/s)",
+        results.iter().filter(|(_, result)| result.is_ok()).count(),
+        results.len(),
+        HumanBytes(total_bytes),
+        elapsed.as_secs_f64(),
+        HumanBytes(throughput as u64)
+    );
+
+    let mut template_folders = Vec::new();
+    let mut failed = false;
+
+    for (index, result) in results {
+        match result {
+            Ok(packed) => {
+                println!();
+                println!("Output archive is located at:");
+                println!("- {}", packed.path);
+                println!("SHA-256 (written to {}):", packed.sidecar);
+                println!("- {}", packed.digest.sha256);
+                println!("Integrity:");
+                println!("- {}", packed.digest.integrity);
+
+                let template_folder = config.get_template_folder(&packed.unity_full);
+                if !template_folders.contains(&template_folder) {
+                    template_folders.push(template_folder);
+                }
+            }
+            Err(e) => {
+                eprintln!();
+                eprintln!("Failed to pack `{}`", jobs[index].0.display());
+                eprintln!("> {}", e);
+                failed = true;
             }
+        }
+    }
+
+    // only gzipped tarballs can be installed into the hub
+    if options.format == pack::Format::Tgz {
+        println!();
+        println!("Copy the .tgz files into:");
+        for template_folder in template_folders {
+            println!("- {}", template_folder);
+        }
+        println!();
+        println!("After copying, completely restart the Unity Hub.");
+    }
+    println!();
+
+    if failed {
+        exit(1);
+    }
+
+    Ok(())
+}
+
+fn verify_project(cmd: VerifyCommand) -> std::io::Result<()> {
+    let path = Path::new(&cmd.file);
+    let expected = match integrity::read_sidecar(path) {
+        Ok(hash) => hash,
+        Err(e) => {
+            eprintln!(
+                "Could not read checksum from `{}`",
+                integrity::sidecar_path(path)
+            );
+            eprintln!("> {}", e);
+            exit(1);
+        }
+    };
+
+    let digest = integrity::digest_file(path)?;
+
+    if digest.sha256 != expected {
+        eprintln!("Checksum mismatch for `{}`", cmd.file);
+        eprintln!("> expected {}", expected);
+        eprintln!("> actual   {}", digest.sha256);
+        exit(1);
+    }
+
+    println!();
+    println!("Checksum OK:");
+    println!("- {}", digest.sha256);
+    println!("Integrity:");
+    println!("- {}", digest.integrity);
+    println!();
+
+    Ok(())
+}
+
+fn inspect_project(cmd: InspectCommand) -> std::io::Result<()> {
+    let inspection = match inspect::inspect(Path::new(&cmd.file)) {
+        Ok(inspection) => inspection,
+        Err(e) => {
+            eprintln!("Could not read template from `{}`", cmd.file);
+            eprintln!("> {}", e);
+            exit(1);
+        }
+    };
+
+    if cmd.json {
+        println!("{}", serde_json::to_string_pretty(&inspection).unwrap());
+    } else {
+        inspection.print();
+    }
+
+    Ok(())
+}
+
+fn unpack_project(cmd: UnpackCommand) -> std::io::Result<()> {
+    let tgz = Path::new(&cmd.file);
+    let package = match inspect::inspect(tgz) {
+        Ok(inspection) => inspection.package,
+        Err(e) => {
+            eprintln!("Could not read template from `{}`", cmd.file);
+            eprintln!("> {}", e);
+            exit(1);
+        }
+    };
+
+    let package = match bundle::Data::from_value(package) {
+        Ok(package) => package,
+        Err(e) => {
+            eprintln!("`{}` has an invalid package.json", cmd.file);
+            eprintln!("> {}", e);
+            exit(1);
+        }
+    };
+
+    let path = format!(".\\builds\\{}-{}\\", package.name, package.version);
+    let root_dir = Path::new(&path);
+
+    if root_dir.exists() {
+        if !cmd.force {
+            eprintln!("Build folder already exists at `{}`", path);
+            eprintln!("> Pass --force to replace it");
+            exit(1);
+        }
+
+        fs::remove_dir_all(root_dir)?;
+    }
+
+    // the override script is added again when packing
+    archive::extract_tgz(tgz, &root_dir.join("package"), |path| {
+        (path != bundle::BOOTSTRAP_PATH).then(|| path.to_string())
+    })?;
+
+    bundle::restore_manifest(&root_dir.join("package").join("ProjectData~"))?;
+
+    println!();
+    println!("Build folder is located at:");
+    println!("- {}", root_dir.to_str().unwrap());
+    println!();
+    println!("To pack the template, run:");
+    println!("- create-unity-template.exe pack");
+    println!();
+
+    Ok(())
+}
+
+fn validate_project(config: config::Config, cmd: ValidateCommand) -> std::io::Result<()> {
+    let names: Vec<String> = cmd.build.into_iter().collect();
+    let project = select_builds(&names, "Project to validate", false)?.remove(0);
+
+    let report = validate::validate(&config, &project);
+    report.print(project.file_name().unwrap().to_str().unwrap());
+    println!();
+
+    if report.has_errors() {
+        exit(1);
+    }
+
+    Ok(())
+}
+
+fn bump_project(cmd: BumpCommand) -> std::io::Result<()> {
+    let project =
+        select_builds(std::slice::from_ref(&cmd.build), "Project to bump", false)?.remove(0);
+    let package_path = project.join("package");
+
+    let mut data = bundle::read_package(&package_path)?;
 
-            if (!File.Exists(InputPath))
-            {
-                Debug.LogWarning($"Input path does not exist at: {InputPath}");
-                Debug.LogWarning("___ManifestOverride.cs is most likely completed. Remove this file if so.");
-                return;
+    let current = match semver::Version::parse(&data.version) {
+        Ok(version) => version,
+        Err(e) => {
+            eprintln!("Current version `{}` is not valid semver", data.version);
+            eprintln!("> {}", e);
+            exit(1);
+        }
+    };
+
+    let next = match bump::bump(&current, cmd.part, cmd.preid.as_deref()) {
+        Ok(version) => version,
+        Err(e) => {
+            eprintln!("Could not bump `{}`", current);
+            eprintln!("> {}", e);
+            exit(1);
+        }
+    };
+
+    // builds are named `<name>-<version>`
+    let build_name = project.file_name().unwrap().to_str().unwrap();
+    let new_name = match build_name.strip_suffix(&format!("-{}", current)) {
+        Some(name) => format!("{}-{}", name, next),
+        None => format!("{}-{}", data.name, next),
+    };
+    let new_project = project.with_file_name(&new_name);
+
+    if new_project.exists() {
+        eprintln!("Build folder already exists at `{}`", new_project.display());
+        exit(1);
+    }
+
+    data.version = next.to_string();
+    bundle::write_package(&package_path, &data)?;
+
+    let changelog_path = package_path.join("CHANGELOG.md");
+    let changelog = fs::read_to_string(&changelog_path).unwrap_or_default();
+    fs::write(
+        &changelog_path,
+        bump::add_changelog_entry(&changelog, &next, &date::Date::today().to_iso()),
+    )?;
+
+    let readme_path = package_path.join("README.md");
+    if let Ok(readme) = fs::read_to_string(&readme_path) {
+        fs::write(
+            &readme_path,
+            bump::set_readme_version(&readme, &current, &next),
+        )?;
+    }
+
+    fs::rename(&project, &new_project)?;
+
+    println!();
+    println!("Bumped {} -> {}", current, next);
+    println!();
+    println!("Build folder is located at:");
+    println!("- {}", new_project.display());
+    println!();
+
+    Ok(())
+}
+
+fn retarget_project(config: config::Config, cmd: RetargetCommand) -> std::io::Result<()> {
+    let project = select_builds(
+        std::slice::from_ref(&cmd.build),
+        "Project to retarget",
+        false,
+    )?
+    .remove(0);
+
+    // exact editor, or the only one installed for a release line such as `2022.3`
+    let versions = config::load_versions(&config);
+    let full_name = |version: &config::Version| format!("{}.{}", version.major, version.minor);
+    let matches: Vec<&config::Version> = match versions
+        .iter()
+        .find(|version| full_name(version) == cmd.unity)
+    {
+        Some(version) => vec![version],
+        None => versions
+            .iter()
+            .filter(|version| version.major == cmd.unity)
+            .collect(),
+    };
+
+    let editor = match matches.as_slice() {
+        [editor] => *editor,
+        [] => {
+            eprintln!(
+                "Editor {} is not installed in `{}`",
+                cmd.unity, config.essentials.unity_hub_path
+            );
+            exit(1);
+        }
+        editors => {
+            eprintln!("More than one editor matches {}", cmd.unity);
+            for editor in editors {
+                eprintln!("- {}", full_name(editor));
             }
+            exit(1);
+        }
+    };
+    let unity_full = full_name(editor);
 
-            var targetPath = Path.Join(Application.dataPath, "..\\Packages\\manifest.json");
+    let package_path = project.join("package");
+    let project_data = package_path.join("ProjectData~");
+    let mut data = bundle::read_package(&package_path)?;
 
-            File.Copy(InputPath, targetPath, true);
+    let mut manifest: serde_json::Value = serde_json::from_str(&fs::read_to_string(
+        project_data.join("Packages").join("manifest.json"),
+    )?)?;
+    let old_dependencies: deps::Packages = manifest["dependencies"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, version)| Some((name.clone(), version.as_str()?.to_string())))
+        .collect();
 
-            // delete this file
-            AssetDatabase.DeleteAsset("Assets/___ManifestOverride.cs");
-            AssetDatabase.DeleteAsset("Assets/manifest.json");
-            AssetDatabase.SaveAssets();
-            AssetDatabase.Refresh();
-        };
+    let (dependencies, changes) = deps::remap(
+        &old_dependencies,
+        &deps::editor_packages(&config, editor),
+        cmd.remove_unbundled,
+    );
+
+    let new_name = format!("{}-{}-{}", data.name, unity_full, data.version);
+    let new_project = project.with_file_name(&new_name);
+
+    if new_project.exists() {
+        eprintln!("Build folder already exists at `{}`", new_project.display());
+        exit(1);
+    }
+
+    let old_unity_full = data.unity_full().to_string();
+    data.unity = Some(editor.major.clone());
+    data.unity_full = Some(unity_full.clone());
+    data.dependencies = Some(json!(dependencies));
+    manifest["dependencies"] = json!(dependencies);
+
+    bundle::copy_dir_all(&project, &new_project)?;
+    let package_path = new_project.join("package");
+    bundle::write_package(&package_path, &data)?;
+    bundle::write_manifest(&package_path.join("ProjectData~"), &manifest)?;
+
+    // the README lists the editor and the dependencies
+    match documents::render_readme(&config, &data) {
+        Ok(readme) => fs::write(package_path.join("README.md"), readme)?,
+        Err(e) => eprintln!("Warning: could not regenerate README.md: {}", e),
+    }
+
+    println!();
+    println!("Retargeted {} -> {}:", old_unity_full, unity_full);
+
+    let mut unchanged = 0;
+    for (name, change) in &changes {
+        let old = &old_dependencies[name];
+
+        match change {
+            deps::Change::Unchanged | deps::Change::Kept => unchanged += 1,
+            deps::Change::Upgraded(version) => {
+                println!("- {}: {} -> {} (upgraded)", name, old, version)
+            }
+            deps::Change::Downgraded(version) => println!(
+                "- {}: {} -> {} ({})",
+                name,
+                old,
+                version,
+                style("downgraded").yellow()
+            ),
+            deps::Change::NotBundled => println!(
+                "- {}: {} {}, it comes from the registry",
+                name,
+                old,
+                style(format!("not bundled with {}", unity_full)).yellow()
+            ),
+            deps::Change::Removed => println!(
+                "- {}: {} {} (not bundled with {})",
+                name,
+                old,
+                style("removed").red(),
+                unity_full
+            ),
+        }
     }
+    println!("- {} unchanged", unchanged);
+
+    println!();
+    println!("Build folder is located at:");
+    println!("- {}", new_project.display());
+    println!();
+
+    Ok(())
 }
-    "#.trim_start().replace("FULL_SCENE_PATH", &data.as_object().unwrap()["defaultScene"].as_str().unwrap());
-
-    std::fs::write(".\\___ManifestOverride.cs", &contents)
-        .unwrap_or_else(|e| panic!("Failed to create packer class file: {:?}", e));
-    let mut override_file = File::open(".\\___ManifestOverride.cs").unwrap();
-    tar.append_file(
-        "package\\ProjectData~\\Assets\\___ManifestOverride.cs",
-        &mut override_file,
-    )
-    .unwrap_or_else(|e| panic!("Failed to pack tar file: {:?}", e));
-    std::fs::remove_file(".\\___ManifestOverride.cs")
-        .unwrap_or_else(|e| panic!("Failed to delete packer class file: {:?}", e));
 
-    let template_folder = config.get_template_folder(version);
+fn vendor_project(config: config::Config, cmd: VendorCommand) -> std::io::Result<()> {
+    let project =
+        select_builds(std::slice::from_ref(&cmd.build), "Project to vendor", false)?.remove(0);
+
+    let mut folders: Vec<PathBuf> = cmd.from.iter().map(PathBuf::from).collect();
+    if let Some(source) = &cmd.project {
+        folders.push(Path::new(source).join("Library").join("PackageCache"));
+    }
+
+    if folders.is_empty() {
+        eprintln!("Pass `--from` or `--project` to say where to copy packages from");
+        exit(1);
+    }
+
+    let options = vendor::Options {
+        folders,
+        packages: cmd.packages,
+        embed: cmd.embed,
+        mtime: archive::resolve_mtime(None),
+    };
+
+    let report = match vendor::vendor(&config, &project, &options) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Could not vendor packages into `{}`", project.display());
+            eprintln!("> {}", e);
+            exit(1);
+        }
+    };
 
     println!();
-    println!("Output .tgz is located at:");
-    println!("- {}", path);
+    if report.vendored.is_empty() {
+        println!("Nothing to vendor, every dependency resolves without the registry.");
+        return Ok(());
+    }
+
+    println!(
+        "Vendored into {}:",
+        project.file_name().unwrap().to_string_lossy()
+    );
+    for package in &report.vendored {
+        println!(
+            "- {} {} -> {} (from {})",
+            package.name,
+            package.version,
+            package.target,
+            package.source.display()
+        );
+    }
+
+    for package in &report.online {
+        println!(
+            "- {}: {} is needed by a vendored package and still comes from the registry",
+            style("warning").yellow(),
+            package
+        );
+    }
     println!();
-    println!("Copy the .tgz file into:");
-    println!("- {}", template_folder);
+
+    Ok(())
+}
+
+fn publish_project(config: config::Config, cmd: PublishCommand) -> std::io::Result<()> {
+    let Some(url) = cmd.registry.or(config.registry.url) else {
+        eprintln!("No registry to publish to, set `url` under [registry] in config.toml or pass `--registry`");
+        exit(1);
+    };
+
+    let token_env = config
+        .registry
+        .token_env
+        .unwrap_or_else(|| publish::DEFAULT_TOKEN_ENV.to_string());
+    let token = match std::env::var(&token_env) {
+        Ok(token) if !token.is_empty() => token,
+        _ => {
+            eprintln!("Set `{}` to the registry's auth token", token_env);
+            exit(1);
+        }
+    };
+
+    let path = Path::new(&cmd.file);
+    let inspection = match inspect::inspect(path) {
+        Ok(inspection) => inspection,
+        Err(e) => {
+            eprintln!("Could not read template from `{}`", cmd.file);
+            eprintln!("> {}", e);
+            exit(1);
+        }
+    };
+
+    let package = inspection.package;
+    let (Some(name), Some(version)) = (package["name"].as_str(), package["version"].as_str())
+    else {
+        eprintln!("`{}` has no name or version in its package.json", cmd.file);
+        exit(1);
+    };
+
+    if semver::Version::parse(version).is_err() {
+        eprintln!("Version `{}` is not valid semver", version);
+        exit(1);
+    }
+
+    let digest = integrity::digest_file(path)?;
+
+    // don't upload an archive that changed since it was packed
+    if Path::new(&integrity::sidecar_path(path)).exists() {
+        let expected = integrity::read_sidecar(path)?;
+        if digest.sha256 != expected {
+            eprintln!("Checksum mismatch for `{}`", cmd.file);
+            eprintln!("> expected {}", expected);
+            eprintln!("> actual   {}", digest.sha256);
+            exit(1);
+        }
+    }
+
+    let registry = publish::Registry::new(&url, token);
+    let tarball = fs::read(path)?;
+    if let Err(e) = registry.publish(&package, &tarball, &digest, &cmd.tag) {
+        eprintln!("Could not publish {} {}", name, version);
+        eprintln!("> {}", e);
+        exit(1);
+    }
+
     println!();
-    println!("After copying, completely restart the Unity Hub.");
+    println!("Published {} {}:", name, version);
+    println!("- Registry: {}", registry.url());
+    println!("- Tag: {}", cmd.tag);
+    println!("- Integrity: {}", digest.integrity);
     println!();
 
     Ok(())
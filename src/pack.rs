@@ -0,0 +1,226 @@
+use crate::{archive, bundle, integrity};
+use flate2::{write::GzEncoder, Compression};
+use std::{
+    fs,
+    io::{self, Seek, Write},
+    path::Path,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// Uncompressed tarball
+    Tar,
+    /// Gzipped tarball, the only format the Unity Hub loads
+    Tgz,
+    /// Zip archive for sharing outside the Hub
+    Zip,
+}
+
+impl Format {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Tar => "tar",
+            Format::Tgz => "tgz",
+            Format::Zip => "zip",
+        }
+    }
+}
+
+pub struct Options {
+    pub format: Format,
+    /// 0 (store) to 9 (smallest), ignored for `tar`
+    pub level: u32,
+    pub mtime: u64,
+}
+
+pub struct Packed {
+    pub path: String,
+    pub sidecar: String,
+    pub digest: integrity::Digest,
+    pub unity_full: String,
+}
+
+/// Bytes that will be read while packing `build`, used to size progress bars.
+pub fn input_size(build: &Path) -> io::Result<u64> {
+    archive::dir_size(&build.join("package"))
+}
+
+/// Packs `builds/<build>/package` into `<output_path>/<build>.<extension>`.
+pub fn pack(
+    build: &Path,
+    output_path: &str,
+    options: &Options,
+    on_read: &mut dyn FnMut(u64),
+) -> io::Result<Packed> {
+    let build_name = build.file_name().unwrap().to_str().unwrap();
+    let project_path = build.join("package");
+
+    // load package.json from project
+    let data = bundle::read_package(&project_path)?;
+    let bootstrap = bundle::bootstrap_script(data.default_scene());
+
+    let path = format!(
+        "{}\\{}.{}",
+        output_path,
+        build_name,
+        options.format.extension()
+    );
+
+    // pack into a temp file first, so a failed pack never leaves a partial archive behind
+    let temp = tempfile::NamedTempFile::new_in(output_path)?;
+    let temp = match options.format {
+        Format::Tar => write_tar(temp, &project_path, &bootstrap, options.mtime, on_read)?,
+        Format::Tgz => {
+            let enc = GzEncoder::new(temp, Compression::new(options.level));
+            write_tar(enc, &project_path, &bootstrap, options.mtime, on_read)?.finish()?
+        }
+        Format::Zip => write_zip(temp, &project_path, &bootstrap, options, on_read)?,
+    };
+    temp.persist(&path)?;
+
+    // temp files are created owner-only, give the archive the usual permissions
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644))?;
+    }
+
+    let digest = integrity::digest_file(Path::new(&path))?;
+    let sidecar = integrity::write_sidecar(Path::new(&path), &digest)?;
+
+    Ok(Packed {
+        path,
+        sidecar,
+        digest,
+        unity_full: data.unity_full().to_string(),
+    })
+}
+
+fn write_tar<W: Write>(
+    writer: W,
+    project_path: &Path,
+    bootstrap: &str,
+    mtime: u64,
+    on_read: &mut dyn FnMut(u64),
+) -> io::Result<W> {
+    let mut tar = tar::Builder::new(writer);
+    archive::append_dir_sorted(&mut tar, "package", project_path, mtime, on_read)?;
+    archive::append_data(
+        &mut tar,
+        &format!("package/{}", bundle::BOOTSTRAP_PATH),
+        bootstrap.len() as u64,
+        bootstrap.as_bytes(),
+        mtime,
+    )?;
+
+    tar.into_inner()
+}
+
+fn write_zip<W: Write + Seek>(
+    writer: W,
+    project_path: &Path,
+    bootstrap: &str,
+    options: &Options,
+    on_read: &mut dyn FnMut(u64),
+) -> io::Result<W> {
+    let file_options = archive::zip_options(options.level, options.mtime);
+
+    let mut zip = zip::ZipWriter::new(writer);
+    archive::zip_dir_sorted(&mut zip, "package", project_path, file_options, on_read)?;
+    archive::zip_data(
+        &mut zip,
+        &format!("package/{}", bundle::BOOTSTRAP_PATH),
+        bootstrap.as_bytes(),
+        file_options,
+    )?;
+
+    Ok(zip.finish()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    fn pack_tgz(build: &Path, output: &Path) -> Vec<u8> {
+        let options = Options {
+            format: Format::Tgz,
+            level: 6,
+            mtime: 1234567890,
+        };
+        let packed = pack(build, output.to_str().unwrap(), &options, &mut |_| {}).unwrap();
+        fs::read(packed.path).unwrap()
+    }
+
+    #[test]
+    fn packs_reproducibly() {
+        let dir = tempfile::tempdir().unwrap();
+        let build = dir.path().join("com.acme.template.demo-1.0.0");
+        let package = build.join("package");
+        fs::create_dir_all(package.join("ProjectData~/Assets/Scenes")).unwrap();
+        fs::write(
+            package.join("package.json"),
+            r#"{ "name": "com.acme.template.demo", "version": "1.0.0" }"#,
+        )
+        .unwrap();
+        fs::write(
+            package.join("ProjectData~/Assets/Scenes/Main.unity"),
+            "scene",
+        )
+        .unwrap();
+        fs::write(package.join("README.md"), "# Demo").unwrap();
+        let output = dir.path().join("out");
+        fs::create_dir(&output).unwrap();
+
+        let first = pack_tgz(&build, &output);
+
+        // the file system's times don't end up in the archive
+        fs::File::options()
+            .write(true)
+            .open(package.join("README.md"))
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(3600))
+            .unwrap();
+
+        let second = pack_tgz(&build, &output);
+        assert!(first == second, "packing twice gave different bytes");
+
+        let path = dir.path().join("second.tgz");
+        fs::write(&path, &second).unwrap();
+        let mut archive = archive::open_tgz(&path).unwrap();
+
+        let mut names = Vec::new();
+        for entry in archive.entries().unwrap() {
+            let entry = entry.unwrap();
+            let header = entry.header();
+            let name = entry.path().unwrap().to_string_lossy().to_string();
+
+            assert_eq!(header.uid().unwrap(), 0, "{}", name);
+            assert_eq!(header.gid().unwrap(), 0, "{}", name);
+            assert_eq!(header.mtime().unwrap(), 1234567890, "{}", name);
+            let mode = if header.entry_type().is_dir() {
+                0o755
+            } else {
+                0o644
+            };
+            assert_eq!(header.mode().unwrap(), mode, "{}", name);
+
+            names.push(name);
+        }
+
+        // sorted, with the bootstrap script appended last
+        assert_eq!(
+            names,
+            [
+                "package/",
+                "package/ProjectData~/",
+                "package/ProjectData~/Assets/",
+                "package/ProjectData~/Assets/Scenes/",
+                "package/ProjectData~/Assets/Scenes/Main.unity",
+                "package/README.md",
+                "package/package.json",
+                "package/ProjectData~/Assets/___ManifestOverride.cs",
+            ]
+        );
+    }
+}
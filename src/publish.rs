@@ -0,0 +1,357 @@
+use crate::integrity::Digest;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::json;
+use std::time::Duration;
+
+/// Used when the config doesn't name the token's environment variable
+pub const DEFAULT_TOKEN_ENV: &str = "UPM_REGISTRY_TOKEN";
+
+pub struct Registry {
+    agent: ureq::Agent,
+    /// Base url without a trailing slash
+    url: String,
+    token: String,
+}
+
+impl Registry {
+    pub fn new(url: &str, token: String) -> Self {
+        Registry {
+            agent: ureq::AgentBuilder::new()
+                .timeout_connect(Duration::from_secs(10))
+                .build(),
+            url: url.trim_end_matches('/').to_string(),
+            token,
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    // scoped names keep their `@` but escape the slash, like npm does
+    fn package_url(&self, name: &str) -> String {
+        format!("{}/{}", self.url, name.replace('/', "%2f"))
+    }
+
+    /// Whether `version` of `name` is already in the registry.
+    pub fn has_version(&self, name: &str, version: &str) -> Result<bool, String> {
+        let response = match self
+            .agent
+            .get(&self.package_url(name))
+            .set("Accept", "application/json")
+            .set("Authorization", &format!("Bearer {}", self.token))
+            .call()
+        {
+            Ok(response) => response,
+            Err(ureq::Error::Status(404, _)) => return Ok(false),
+            Err(e) => return Err(describe(e)),
+        };
+
+        let document: serde_json::Value = response
+            .into_json()
+            .map_err(|e| format!("could not parse the registry's answer: {}", e))?;
+
+        Ok(document["versions"].get(version).is_some())
+    }
+
+    /// Uploads a packed template, tagging its version with `tag`.
+    ///
+    /// Refuses a version that is already in the registry.
+    pub fn publish(
+        &self,
+        package: &serde_json::Value,
+        tarball: &[u8],
+        digest: &Digest,
+        tag: &str,
+    ) -> Result<(), String> {
+        let name = package["name"].as_str().unwrap_or_default();
+        let version = package["version"].as_str().unwrap_or_default();
+
+        if self.has_version(name, version)? {
+            return Err(format!(
+                "{} {} is already published to `{}`, bump the version first",
+                name, version, self.url
+            ));
+        }
+
+        let document = document(&self.url, package, tarball, digest, tag);
+
+        self.agent
+            .put(&self.package_url(name))
+            .set("Authorization", &format!("Bearer {}", self.token))
+            .send_json(document)
+            .map(|_| ())
+            .map_err(|e| match e {
+                ureq::Error::Status(409, _) => {
+                    "the registry refused the upload because the version already exists".to_string()
+                }
+                e => describe(e),
+            })
+    }
+}
+
+/// The body `npm publish` sends: the package document with one version and its tarball attached.
+pub fn document(
+    registry: &str,
+    package: &serde_json::Value,
+    tarball: &[u8],
+    digest: &Digest,
+    tag: &str,
+) -> serde_json::Value {
+    let name = package["name"].as_str().unwrap_or_default();
+    let version = package["version"].as_str().unwrap_or_default();
+    // npm names tarballs after the unscoped part of the name
+    let file_name = format!(
+        "{}-{}.tgz",
+        name.rsplit('/').next().unwrap_or(name),
+        version
+    );
+
+    let mut manifest = package.clone();
+    manifest["_id"] = json!(format!("{}@{}", name, version));
+    manifest["dist"] = json!({
+        "integrity": digest.integrity,
+        "shasum": digest.shasum,
+        "tarball": format!("{}/{}/-/{}", registry, name, file_name),
+    });
+
+    json!({
+        "_id": name,
+        "name": name,
+        "description": package["description"],
+        "dist-tags": { tag: version },
+        "versions": { version: manifest },
+        "access": null,
+        "_attachments": {
+            file_name: {
+                "content_type": "application/octet-stream",
+                "data": STANDARD.encode(tarball),
+                "length": tarball.len(),
+            }
+        },
+    })
+}
+
+fn describe(error: ureq::Error) -> String {
+    match error {
+        ureq::Error::Status(code, response) => {
+            let url = response.get_url().to_string();
+            let body = response.into_string().unwrap_or_default();
+            // npm registries answer with `{ "error": "..." }`
+            let message = serde_json::from_str::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|body| body["error"].as_str().map(|error| error.to_string()))
+                .unwrap_or(body);
+
+            format!("`{}` answered {}: {}", url, code, message.trim())
+        }
+        ureq::Error::Transport(transport) => transport.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integrity;
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        thread::{self, JoinHandle},
+    };
+
+    struct Request {
+        method: String,
+        path: String,
+        authorization: Option<String>,
+        body: String,
+    }
+
+    /// Answers one request per response, in order, and returns what it was sent.
+    fn serve(responses: Vec<(u16, &'static str)>) -> (String, JoinHandle<Vec<Request>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::new();
+
+            for (status, body) in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let mut parts = line.split_whitespace();
+                let method = parts.next().unwrap().to_string();
+                let path = parts.next().unwrap().to_string();
+
+                let mut length = 0;
+                let mut authorization = None;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    let header = header.trim_end();
+                    if header.is_empty() {
+                        break;
+                    }
+
+                    let (key, value) = header.split_once(": ").unwrap();
+                    match key.to_ascii_lowercase().as_str() {
+                        "content-length" => length = value.parse().unwrap(),
+                        "authorization" => authorization = Some(value.to_string()),
+                        _ => {}
+                    }
+                }
+
+                let mut request_body = vec![0; length];
+                reader.read_exact(&mut request_body).unwrap();
+
+                write!(
+                    stream,
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                )
+                .unwrap();
+
+                requests.push(Request {
+                    method,
+                    path,
+                    authorization,
+                    body: String::from_utf8(request_body).unwrap(),
+                });
+            }
+
+            requests
+        });
+
+        (url, handle)
+    }
+
+    fn package() -> serde_json::Value {
+        json!({
+            "name": "com.acme.template.demo",
+            "version": "1.0.0",
+            "description": "Demo template",
+            "type": "template"
+        })
+    }
+
+    fn digest(tarball: &[u8]) -> Digest {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("demo.tgz");
+        std::fs::write(&path, tarball).unwrap();
+        integrity::digest_file(&path).unwrap()
+    }
+
+    #[test]
+    fn document_has_tags_attachment_and_integrity() {
+        let tarball = b"template";
+        let document = document(
+            "http://registry",
+            &package(),
+            tarball,
+            &digest(tarball),
+            "beta",
+        );
+
+        assert_eq!(document["name"], "com.acme.template.demo");
+        assert_eq!(document["dist-tags"], json!({ "beta": "1.0.0" }));
+
+        let version = &document["versions"]["1.0.0"];
+        assert_eq!(version["_id"], "com.acme.template.demo@1.0.0");
+        assert_eq!(version["type"], "template");
+        assert_eq!(
+            version["dist"]["integrity"],
+            "sha512-R/QsdzyANcHwg0Hq3mFi08wYHwSGLDMNOAkUKcfPHK2ZIf7WTf0S82y0/JIIYW+HdPMZEvUUokqh65lxEGZx5w=="
+        );
+        assert_eq!(
+            version["dist"]["shasum"],
+            "3226ecbe650213a49cd03ae67140750e4f340083"
+        );
+        assert_eq!(
+            version["dist"]["tarball"],
+            "http://registry/com.acme.template.demo/-/com.acme.template.demo-1.0.0.tgz"
+        );
+
+        let attachment = &document["_attachments"]["com.acme.template.demo-1.0.0.tgz"];
+        assert_eq!(attachment["data"], "dGVtcGxhdGU=");
+        assert_eq!(attachment["length"], tarball.len());
+    }
+
+    #[test]
+    fn missing_package_has_no_versions() {
+        let (url, server) = serve(vec![(404, r#"{"error":"not found"}"#)]);
+        let registry = Registry::new(&url, "secret".to_string());
+
+        assert_eq!(
+            registry.has_version("com.acme.template.demo", "1.0.0"),
+            Ok(false)
+        );
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests[0].method, "GET");
+        assert_eq!(requests[0].path, "/com.acme.template.demo");
+        assert_eq!(requests[0].authorization.as_deref(), Some("Bearer secret"));
+    }
+
+    #[test]
+    fn publishes_new_version() {
+        let (url, server) = serve(vec![
+            (200, r#"{"versions":{"0.9.0":{}}}"#),
+            (201, r#"{"ok":"created"}"#),
+        ]);
+        let registry = Registry::new(&format!("{}/", url), "secret".to_string());
+        let tarball = b"template";
+
+        registry
+            .publish(&package(), tarball, &digest(tarball), "latest")
+            .unwrap();
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests[1].method, "PUT");
+        assert_eq!(requests[1].path, "/com.acme.template.demo");
+        assert_eq!(requests[1].authorization.as_deref(), Some("Bearer secret"));
+
+        let body: serde_json::Value = serde_json::from_str(&requests[1].body).unwrap();
+        assert_eq!(
+            body,
+            document(&url, &package(), tarball, &digest(tarball), "latest")
+        );
+    }
+
+    #[test]
+    fn refuses_existing_version() {
+        let (url, server) = serve(vec![(200, r#"{"versions":{"1.0.0":{}}}"#)]);
+        let registry = Registry::new(&url, "secret".to_string());
+        let tarball = b"template";
+
+        let error = registry
+            .publish(&package(), tarball, &digest(tarball), "latest")
+            .unwrap_err();
+        assert!(error.contains("already published"), "{}", error);
+
+        // nothing is uploaded
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "GET");
+    }
+
+    #[test]
+    fn refuses_when_registry_conflicts() {
+        let (url, server) = serve(vec![
+            (404, r#"{"error":"not found"}"#),
+            (409, r#"{"error":"this package is already present"}"#),
+        ]);
+        let registry = Registry::new(&url, "secret".to_string());
+        let tarball = b"template";
+
+        let error = registry
+            .publish(&package(), tarball, &digest(tarball), "latest")
+            .unwrap_err();
+        assert!(error.contains("already exists"), "{}", error);
+
+        let requests = server.join().unwrap();
+        assert_eq!(requests[1].method, "PUT");
+    }
+}
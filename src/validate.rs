@@ -0,0 +1,464 @@
+use crate::{bundle, config, deps, git, images};
+use console::style;
+use std::{fmt, fs, path::Path};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "{}", style("info").cyan()),
+            Severity::Warning => write!(f, "{}", style("warning").yellow()),
+            Severity::Error => write!(f, "{}", style("error").red()),
+        }
+    }
+}
+
+pub struct Issue {
+    pub severity: Severity,
+    pub message: String,
+}
+
+#[derive(Default)]
+pub struct Report {
+    pub issues: Vec<Issue>,
+}
+
+impl Report {
+    fn push(&mut self, severity: Severity, message: impl Into<String>) {
+        self.issues.push(Issue {
+            severity,
+            message: message.into(),
+        });
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.issues
+            .iter()
+            .any(|issue| issue.severity == Severity::Error)
+    }
+
+    pub fn print(&self, build_name: &str) {
+        println!();
+        println!("Validating {}:", build_name);
+
+        if self.issues.is_empty() {
+            println!("- {}", style("ok").green());
+            return;
+        }
+
+        for issue in &self.issues {
+            println!("- {}: {}", issue.severity, issue.message);
+        }
+    }
+}
+
+/// Checks a build folder against what the Unity Hub needs to list and open a template.
+pub fn validate(config: &config::Config, build: &Path) -> Report {
+    let mut report = Report::default();
+    let package_path = build.join("package");
+    let project_data = package_path.join("ProjectData~");
+
+    // required files
+    for file in [
+        "package.json",
+        "ProjectData~/Packages/manifest.json",
+        "ProjectData~/ProjectSettings/ProjectSettings.asset",
+    ] {
+        if !package_path.join(file).is_file() {
+            report.push(Severity::Error, format!("missing required file `{}`", file));
+        }
+    }
+
+    if !project_data.join("Assets").join("manifest.json").is_file() {
+        report.push(
+            Severity::Warning,
+            "missing `ProjectData~/Assets/manifest.json`, the manifest override script will do nothing",
+        );
+    }
+
+    let package: serde_json::Value = match fs::read_to_string(package_path.join("package.json"))
+        .map_err(|e| e.to_string())
+        .and_then(|contents| serde_json::from_str(&contents).map_err(|e| e.to_string()))
+    {
+        Ok(package) => package,
+        Err(e) => {
+            report.push(
+                Severity::Error,
+                format!("could not read package.json: {}", e),
+            );
+            return report;
+        }
+    };
+
+    // name
+    let name = package["name"].as_str().unwrap_or_default();
+    let prefix = &config.essentials.package_prefix;
+    if let Err(e) = bundle::validate_package_name(name) {
+        report.push(Severity::Error, e);
+    } else if !name.starts_with(prefix.as_str()) || name.len() == prefix.len() {
+        report.push(
+            Severity::Error,
+            format!("package name `{}` does not match `{}*`", name, prefix),
+        );
+    }
+
+    if let Err(e) = bundle::validate_package_prefix(prefix) {
+        report.push(Severity::Warning, e);
+    }
+
+    // type
+    match package["type"].as_str() {
+        Some("template") => {}
+        Some(kind) => report.push(
+            Severity::Warning,
+            format!("`type` is `{}`, the Hub expects `template`", kind),
+        ),
+        None => report.push(
+            Severity::Info,
+            "no `type` is set, the Hub expects `template`",
+        ),
+    }
+
+    // version
+    let version = package["version"].as_str().unwrap_or_default();
+    if let Err(e) = semver::Version::parse(version) {
+        report.push(
+            Severity::Error,
+            format!("version `{}` is not valid semver: {}", version, e),
+        );
+    }
+
+    // unity
+    let unity = package["unity"].as_str().unwrap_or_default();
+    let unity_full = package["unityFull"].as_str().unwrap_or_default();
+    let editor = config::Version::from_name(unity_full);
+
+    if !unity_full.starts_with(unity) || unity.is_empty() {
+        report.push(
+            Severity::Error,
+            format!(
+                "`unity` ({}) does not match `unityFull` ({})",
+                unity, unity_full
+            ),
+        );
+    }
+
+    let editor = editor.filter(|_| {
+        Path::new(&config.essentials.unity_hub_path)
+            .join(unity_full)
+            .is_dir()
+    });
+    if editor.is_none() {
+        report.push(
+            Severity::Warning,
+            format!(
+                "editor {} is not installed in `{}`",
+                unity_full, config.essentials.unity_hub_path
+            ),
+        );
+    }
+
+    // defaultScene
+    match package["defaultScene"].as_str().unwrap_or_default() {
+        "" => report.push(Severity::Warning, "no default scene is set"),
+        scene if !scene.starts_with("Assets/") => report.push(
+            Severity::Error,
+            format!("default scene `{}` is not inside `Assets/`", scene),
+        ),
+        scene if !project_data.join(scene).is_file() => report.push(
+            Severity::Error,
+            format!("default scene `{}` does not exist in ProjectData~", scene),
+        ),
+        _ => {}
+    }
+
+    // images
+    for (key, kind) in [
+        ("previewImage", images::Kind::Preview),
+        ("thumbnail", images::Kind::Thumbnail),
+    ] {
+        let Some(image) = package[key].as_str() else {
+            continue;
+        };
+
+        let path = package_path.join(image);
+        if !path.is_file() {
+            report.push(
+                Severity::Error,
+                format!("`{}` points to a missing file `{}`", key, image),
+            );
+        } else if let Err(e) = images::load(path.to_str().unwrap(), kind) {
+            report.push(Severity::Error, e);
+        }
+    }
+
+    // dependencies
+    let manifest = fs::read_to_string(project_data.join("Packages").join("manifest.json"))
+        .ok()
+        .and_then(|contents| serde_json::from_str::<serde_json::Value>(&contents).ok());
+    let dependencies = manifest
+        .as_ref()
+        .and_then(|manifest| manifest["dependencies"].as_object());
+
+    let bundled: Vec<config::BuiltInPackage> = match &editor {
+        Some(editor) if Path::new(&config.get_editor_folder(editor)).is_dir() => {
+            config::load_dependencies(config, editor)
+        }
+        _ => Vec::new(),
+    };
+
+    for (name, version) in dependencies.into_iter().flatten() {
+        let version = version.as_str().unwrap_or_default();
+
+        if deps::is_module(name) {
+            continue;
+        }
+
+        if let Some(path) = version
+            .strip_prefix("file:")
+            .filter(|_| !git::is_git(version))
+        {
+            if !project_data.join("Packages").join(path).exists() {
+                report.push(
+                    Severity::Error,
+                    format!("`{}` points to a missing local package `{}`", name, path),
+                );
+            }
+            continue;
+        }
+
+        if git::is_git(version) {
+            match git::parse(version) {
+                Ok(url) if url.revision.is_none() => report.push(
+                    Severity::Warning,
+                    format!(
+                        "`{}` is fetched from the default branch of `{}`, pin a revision with `#`",
+                        name, url.repo
+                    ),
+                ),
+                Ok(_) => report.push(
+                    Severity::Info,
+                    format!("`{}` is fetched from git when the project opens", name),
+                ),
+                Err(e) => report.push(
+                    Severity::Error,
+                    format!("`{}` has an invalid git url: {}", name, e),
+                ),
+            }
+            continue;
+        }
+
+        if semver::Version::parse(version).is_err() {
+            report.push(
+                Severity::Error,
+                format!("`{}` has an invalid version `{}`", name, version),
+            );
+            continue;
+        }
+
+        if editor.is_none() {
+            continue;
+        }
+
+        let bundled_version = bundled
+            .iter()
+            .filter(|package| package.name.as_deref() == Some(name))
+            .filter_map(|package| package.version.as_deref())
+            .next();
+
+        match bundled_version {
+            Some(bundled_version) if bundled_version == version => {}
+            Some(bundled_version) => report.push(
+                Severity::Warning,
+                format!(
+                    "`{}` {} is not the version bundled with the editor ({}), it needs the registry",
+                    name, version, bundled_version
+                ),
+            ),
+            None => report.push(
+                Severity::Info,
+                format!(
+                    "`{}` {} is not bundled with the editor, it needs the registry",
+                    name, version
+                ),
+            ),
+        }
+    }
+
+    report
+        .issues
+        .sort_by_key(|issue| std::cmp::Reverse(issue.severity));
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A build with only warnings: its editor isn't installed and it has no default scene.
+    fn build(dir: &Path) -> serde_json::Value {
+        let project_data = dir.join("package").join("ProjectData~");
+        fs::create_dir_all(project_data.join("Packages")).unwrap();
+        fs::create_dir_all(project_data.join("ProjectSettings")).unwrap();
+        fs::create_dir_all(project_data.join("Assets").join("Scenes")).unwrap();
+        fs::write(
+            project_data
+                .join("ProjectSettings")
+                .join("ProjectSettings.asset"),
+            "",
+        )
+        .unwrap();
+        fs::write(
+            project_data
+                .join("Assets")
+                .join("Scenes")
+                .join("Main.unity"),
+            "",
+        )
+        .unwrap();
+        fs::create_dir(project_data.join("Packages").join("com.acme.local")).unwrap();
+
+        json!({
+            "name": "com.acme.template.demo",
+            "version": "1.0.0",
+            "type": "template",
+            "unity": "2022.3",
+            "unityFull": "2022.3.5f1",
+        })
+    }
+
+    fn validate_with(
+        dir: &Path,
+        package: &serde_json::Value,
+        dependencies: serde_json::Value,
+    ) -> Report {
+        let project_data = dir.join("package").join("ProjectData~");
+        fs::write(
+            dir.join("package").join("package.json"),
+            package.to_string(),
+        )
+        .unwrap();
+        let manifest = json!({ "dependencies": dependencies }).to_string();
+        fs::write(
+            project_data.join("Packages").join("manifest.json"),
+            &manifest,
+        )
+        .unwrap();
+        fs::write(project_data.join("Assets").join("manifest.json"), &manifest).unwrap();
+
+        let config: config::Config = toml::from_str(&format!(
+            "[essentials]\nunity_hub_path = {:?}\ndefault_dependencies = []\npackage_prefix = \"com.acme.template.\"",
+            dir.join("hub").to_str().unwrap()
+        ))
+        .unwrap();
+        validate(&config, dir)
+    }
+
+    fn assert_error(report: &Report, message: &str) {
+        assert!(report.has_errors());
+        assert!(
+            report
+                .issues
+                .iter()
+                .any(|issue| issue.severity == Severity::Error && issue.message.contains(message)),
+            "no error about `{}`",
+            message
+        );
+    }
+
+    #[test]
+    fn warnings_are_not_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let package = build(dir.path());
+
+        let report = validate_with(
+            dir.path(),
+            &package,
+            json!({
+                "com.unity.timeline": "1.7.5",
+                "com.unity.modules.ui": "1.0.0",
+                "com.acme.local": "file:com.acme.local",
+                "com.acme.tools": "https://github.com/acme/tools.git",
+            }),
+        );
+
+        assert!(!report.has_errors());
+        assert!(report
+            .issues
+            .iter()
+            .any(|issue| issue.severity == Severity::Warning));
+    }
+
+    #[test]
+    fn package_json_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let package = build(dir.path());
+
+        let cases = [
+            ("name", json!("com.other.template.demo"), "does not match"),
+            ("version", json!("1.0"), "is not valid semver"),
+            ("unity", json!("2021.3"), "does not match `unityFull`"),
+            (
+                "defaultScene",
+                json!("Scenes/Main.unity"),
+                "is not inside `Assets/`",
+            ),
+            (
+                "defaultScene",
+                json!("Assets/Scenes/Missing.unity"),
+                "does not exist",
+            ),
+        ];
+
+        for (key, value, message) in cases {
+            let mut package = package.clone();
+            package[key] = value;
+            assert_error(&validate_with(dir.path(), &package, json!({})), message);
+        }
+
+        let mut package = package.clone();
+        package["defaultScene"] = json!("Assets/Scenes/Main.unity");
+        assert!(!validate_with(dir.path(), &package, json!({})).has_errors());
+    }
+
+    #[test]
+    fn dependency_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let package = build(dir.path());
+
+        let report = validate_with(
+            dir.path(),
+            &package,
+            json!({ "com.acme.missing": "file:com.acme.missing" }),
+        );
+        assert_error(&report, "points to a missing local package");
+
+        let report = validate_with(
+            dir.path(),
+            &package,
+            json!({ "com.acme.tools": "https://github.com/acme/tools.git#" }),
+        );
+        assert_error(&report, "has an invalid git url");
+    }
+
+    #[test]
+    fn missing_required_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let package = build(dir.path());
+        fs::remove_file(
+            dir.path()
+                .join("package/ProjectData~/ProjectSettings/ProjectSettings.asset"),
+        )
+        .unwrap();
+
+        let report = validate_with(dir.path(), &package, json!({}));
+        assert_error(&report, "ProjectSettings.asset");
+    }
+}
@@ -0,0 +1,525 @@
+use crate::{archive, bundle, config, deps};
+use flate2::{write::GzEncoder, Compression};
+use std::{
+    collections::{BTreeSet, VecDeque},
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+use tar::Builder;
+
+pub struct Options {
+    /// Folders to look for packages in, in order
+    pub folders: Vec<PathBuf>,
+    /// Packages to vendor, every dependency the editor doesn't bundle when empty
+    pub packages: Vec<String>,
+    /// Copies packages in as embedded folders instead of `file:` tarballs
+    pub embed: bool,
+    pub mtime: u64,
+}
+
+pub struct Vendored {
+    pub name: String,
+    pub version: String,
+    pub source: PathBuf,
+    /// The `file:` version in the manifest, or the embedded package folder
+    pub target: String,
+}
+
+#[derive(Default)]
+pub struct Report {
+    pub vendored: Vec<Vendored>,
+    /// Registry dependencies of vendored packages that could not be found, as `<name> <version>`
+    pub online: Vec<String>,
+}
+
+/// Copies registry packages into `builds/<build>/package/ProjectData~/Packages` and points the
+/// manifest and package.json at them.
+///
+/// The registry dependencies of vendored packages come along when they can be found. Nothing is
+/// changed when one of the packages to vendor can't be found.
+pub fn vendor(config: &config::Config, build: &Path, options: &Options) -> Result<Report, String> {
+    let package_path = build.join("package");
+    let project_data = package_path.join("ProjectData~");
+    let packages_folder = project_data.join("Packages");
+    let manifest_path = packages_folder.join("manifest.json");
+
+    let mut data = bundle::read_package(&package_path)
+        .map_err(|e| format!("could not read package.json: {}", e))?;
+    let mut manifest: serde_json::Value = fs::read_to_string(&manifest_path)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| serde_json::from_str(&contents).map_err(|e| e.to_string()))
+        .map_err(|e| format!("could not read `{}`: {}", manifest_path.display(), e))?;
+
+    let dependencies: deps::Packages = manifest["dependencies"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, version)| Some((name.clone(), version.as_str()?.to_string())))
+        .collect();
+
+    // packages bundled with the editor already resolve offline
+    let bundled = match config::Version::from_name(data.unity_full()) {
+        Some(editor) if Path::new(&config.get_editor_folder(&editor)).is_dir() => {
+            deps::editor_packages(config, &editor)
+        }
+        _ => deps::Packages::new(),
+    };
+
+    let requested = if options.packages.is_empty() {
+        dependencies
+            .iter()
+            .filter(|(name, version)| is_registry(name, version))
+            .filter(|(name, version)| bundled.get(*name) != Some(*version))
+            .map(|(name, version)| (name.clone(), version.clone()))
+            .collect()
+    } else {
+        options
+            .packages
+            .iter()
+            .map(|name| match dependencies.get(name) {
+                Some(version) if is_registry(name, version) => Ok((name.clone(), version.clone())),
+                Some(version) => Err(format!(
+                    "`{}` is `{}`, only registry packages can be vendored",
+                    name, version
+                )),
+                None => Err(format!("`{}` is not a dependency of {}", name, data.name)),
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    // vendored packages bring their own registry dependencies along
+    let mut queue: VecDeque<(String, String, bool)> = requested
+        .into_iter()
+        .map(|(name, version)| (name, version, true))
+        .collect();
+    let mut seen = BTreeSet::new();
+    let mut found = Vec::new();
+    let mut missing = Vec::new();
+    let mut report = Report::default();
+
+    while let Some((name, version, direct)) = queue.pop_front() {
+        if !seen.insert(name.clone()) {
+            continue;
+        }
+
+        match find(&options.folders, &name, &version) {
+            Some(location) => {
+                let package = location.package().unwrap_or_default();
+                for (dependency, dependency_version) in package_dependencies(&package) {
+                    // the manifest decides the version of its own dependencies
+                    if !dependencies.contains_key(&dependency)
+                        && bundled.get(&dependency) != Some(&dependency_version)
+                    {
+                        queue.push_back((dependency, dependency_version, false));
+                    }
+                }
+                found.push((name, version, location));
+            }
+            None if direct => missing.push(format!("{} {}", name, version)),
+            None if !bundled.contains_key(&name) => {
+                report.online.push(format!("{} {}", name, version))
+            }
+            None => {}
+        }
+    }
+
+    if !missing.is_empty() {
+        let folders: Vec<String> = options
+            .folders
+            .iter()
+            .map(|folder| format!("`{}`", folder.display()))
+            .collect();
+        return Err(format!(
+            "could not find {} in {}",
+            missing.join(", "),
+            folders.join(", ")
+        ));
+    }
+
+    if found.is_empty() {
+        return Ok(report);
+    }
+
+    let manifest_dependencies = manifest["dependencies"].as_object_mut().unwrap();
+    for (name, version, location) in found {
+        let target = if options.embed {
+            embed(&location, &packages_folder, &name)
+                .map_err(|e| format!("could not embed {}: {}", name, e))?;
+            manifest_dependencies.remove(&name);
+            format!("Packages/{}", name)
+        } else {
+            let target = pack(&location, &packages_folder, &name, &version, options.mtime)
+                .map_err(|e| format!("could not pack {}: {}", name, e))?;
+            manifest_dependencies.insert(name.clone(), serde_json::json!(target));
+            target
+        };
+
+        report.vendored.push(Vendored {
+            name,
+            version,
+            source: location.path().to_path_buf(),
+            target,
+        });
+    }
+
+    data.dependencies = Some(manifest["dependencies"].clone());
+    bundle::write_package(&package_path, &data)
+        .and_then(|_| bundle::write_manifest(&project_data, &manifest))
+        .map_err(|e| format!("could not write the manifest: {}", e))?;
+
+    Ok(report)
+}
+
+fn is_registry(name: &str, version: &str) -> bool {
+    !deps::is_module(name) && semver::Version::parse(version).is_ok()
+}
+
+/// Where a copy of a registry package was found
+enum Location {
+    /// An extracted package, like the ones in `Library/PackageCache`
+    Folder(PathBuf),
+    /// A `.tgz` as downloaded from the registry or made with `npm pack`
+    Tarball(PathBuf),
+}
+
+impl Location {
+    fn path(&self) -> &Path {
+        match self {
+            Location::Folder(path) | Location::Tarball(path) => path,
+        }
+    }
+
+    /// The package's package.json.
+    fn package(&self) -> Option<serde_json::Value> {
+        match self {
+            Location::Folder(path) => read_json(&path.join("package.json")),
+            Location::Tarball(path) => deps::read_tgz_package(path),
+        }
+    }
+}
+
+/// Looks for `name` at `version` in each folder, in order.
+///
+/// A folder can hold `<name>-<version>.tgz` files, or extracted packages named
+/// `<name>@<version>`, `<name>-<version>` or `<name>`. Newer editors add a hash
+/// instead of the version to `Library/PackageCache` folders, so any `<name>@*`
+/// folder with a matching package.json is used too.
+fn find(folders: &[PathBuf], name: &str, version: &str) -> Option<Location> {
+    for folder in folders {
+        let tgz = folder.join(format!("{}-{}.tgz", name, version));
+        if tgz.is_file() {
+            return Some(Location::Tarball(tgz));
+        }
+
+        let candidates = [
+            format!("{}@{}", name, version),
+            format!("{}-{}", name, version),
+            name.to_string(),
+        ]
+        .into_iter()
+        .map(|candidate| folder.join(candidate))
+        .chain(
+            fs::read_dir(folder)
+                .into_iter()
+                .flatten()
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| {
+                    entry
+                        .file_name()
+                        .to_str()
+                        .is_some_and(|file_name| file_name.starts_with(&format!("{}@", name)))
+                })
+                .map(|entry| entry.path()),
+        );
+
+        for candidate in candidates {
+            let Some(package) = read_json(&candidate.join("package.json")) else {
+                continue;
+            };
+
+            if package["name"] == name && package["version"] == version {
+                return Some(Location::Folder(candidate));
+            }
+        }
+    }
+
+    None
+}
+
+/// Registry dependencies of a package, leaving out modules.
+fn package_dependencies(package: &serde_json::Value) -> deps::Packages {
+    package["dependencies"]
+        .as_object()
+        .into_iter()
+        .flatten()
+        .filter_map(|(name, version)| Some((name.clone(), version.as_str()?.to_string())))
+        .filter(|(name, version)| is_registry(name, version))
+        .collect()
+}
+
+/// Copies a package into `packages` as an embedded package folder named after it.
+///
+/// Embedded packages are picked up without a manifest entry.
+fn embed(location: &Location, packages: &Path, name: &str) -> io::Result<()> {
+    let target = packages.join(name);
+    if target.exists() {
+        fs::remove_dir_all(&target)?;
+    }
+
+    match location {
+        Location::Folder(path) => bundle::copy_dir_all(path, &target),
+        Location::Tarball(path) => archive::extract_tgz(path, &target, |file| {
+            Some(file.to_string()).filter(|file| !file.is_empty())
+        }),
+    }
+}
+
+/// Writes a package into `packages` as `<name>-<version>.tgz`.
+///
+/// Returns the manifest version that points to it.
+fn pack(
+    location: &Location,
+    packages: &Path,
+    name: &str,
+    version: &str,
+    mtime: u64,
+) -> io::Result<String> {
+    let file_name = format!("{}-{}.tgz", name, version);
+    let target = packages.join(&file_name);
+
+    match location {
+        Location::Tarball(path) => {
+            fs::copy(path, &target)?;
+        }
+        Location::Folder(path) => {
+            let encoder = GzEncoder::new(File::create(&target)?, Compression::default());
+            let mut tar = Builder::new(encoder);
+            archive::append_dir_sorted(&mut tar, "package", path, mtime, &mut |_| {})?;
+            tar.into_inner()?.finish()?;
+        }
+    }
+
+    Ok(format!("file:{}", file_name))
+}
+
+fn read_json(path: &Path) -> Option<serde_json::Value> {
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    struct Fixture {
+        _dir: tempfile::TempDir,
+        config: config::Config,
+        build: PathBuf,
+        /// Holds `com.acme.a-1.0.0.tgz`
+        mirror: PathBuf,
+        /// Holds `com.acme.b@abc123`, named like newer editors name `Library/PackageCache` folders
+        cache: PathBuf,
+    }
+
+    fn fixture(dependencies: serde_json::Value) -> Fixture {
+        let dir = tempfile::tempdir().unwrap();
+        let config = toml::from_str(&format!(
+            "[essentials]\nunity_hub_path = {:?}\ndefault_dependencies = []",
+            dir.path().join("hub").to_str().unwrap()
+        ))
+        .unwrap();
+
+        let build = dir.path().join("com.acme.template.demo-1.0.0");
+        let project_data = build.join("package").join("ProjectData~");
+        fs::create_dir_all(project_data.join("Packages")).unwrap();
+        fs::create_dir_all(project_data.join("Assets")).unwrap();
+        fs::write(
+            build.join("package").join("package.json"),
+            json!({
+                "name": "com.acme.template.demo",
+                "version": "1.0.0",
+                "dependencies": dependencies,
+            })
+            .to_string(),
+        )
+        .unwrap();
+        fs::write(
+            project_data.join("Packages").join("manifest.json"),
+            json!({ "dependencies": dependencies }).to_string(),
+        )
+        .unwrap();
+
+        let mirror = dir.path().join("mirror");
+        fs::create_dir(&mirror).unwrap();
+        let package = json!({
+            "name": "com.acme.a",
+            "version": "1.0.0",
+            "dependencies": { "com.acme.online": "3.0.0", "com.unity.modules.ui": "1.0.0" },
+        })
+        .to_string();
+        let encoder = GzEncoder::new(
+            File::create(mirror.join("com.acme.a-1.0.0.tgz")).unwrap(),
+            Compression::default(),
+        );
+        let mut tar = Builder::new(encoder);
+        archive::append_data(
+            &mut tar,
+            "package/package.json",
+            package.len() as u64,
+            package.as_bytes(),
+            0,
+        )
+        .unwrap();
+        tar.into_inner().unwrap().finish().unwrap();
+
+        let cache = dir.path().join("cache");
+        fs::create_dir_all(cache.join("com.acme.b@abc123")).unwrap();
+        fs::write(
+            cache.join("com.acme.b@abc123").join("package.json"),
+            json!({ "name": "com.acme.b", "version": "2.0.0" }).to_string(),
+        )
+        .unwrap();
+
+        Fixture {
+            _dir: dir,
+            config,
+            build,
+            mirror,
+            cache,
+        }
+    }
+
+    impl Fixture {
+        fn options(&self, packages: &[&str], embed: bool) -> Options {
+            Options {
+                folders: vec![self.mirror.clone(), self.cache.clone()],
+                packages: packages.iter().map(|name| name.to_string()).collect(),
+                embed,
+                mtime: archive::DEFAULT_MTIME,
+            }
+        }
+
+        fn project_data(&self) -> PathBuf {
+            self.build.join("package").join("ProjectData~")
+        }
+
+        fn dependencies(&self, path: &Path) -> serde_json::Value {
+            read_json(path).unwrap()["dependencies"].clone()
+        }
+    }
+
+    #[test]
+    fn vendors_tarballs_and_cached_folders() {
+        let fixture = fixture(json!({
+            "com.acme.a": "1.0.0",
+            "com.acme.b": "2.0.0",
+            "com.unity.modules.ui": "1.0.0",
+            "com.acme.tools": "https://github.com/acme/tools.git#v1",
+        }));
+
+        let report = vendor(
+            &fixture.config,
+            &fixture.build,
+            &fixture.options(&[], false),
+        )
+        .unwrap();
+
+        let vendored: Vec<(&str, &str)> = report
+            .vendored
+            .iter()
+            .map(|vendored| (vendored.name.as_str(), vendored.target.as_str()))
+            .collect();
+        assert_eq!(
+            vendored,
+            [
+                ("com.acme.a", "file:com.acme.a-1.0.0.tgz"),
+                ("com.acme.b", "file:com.acme.b-2.0.0.tgz"),
+            ]
+        );
+        assert_eq!(
+            report.vendored[1].source,
+            fixture.cache.join("com.acme.b@abc123")
+        );
+        // dependencies of vendored packages that can't be found still come from the registry
+        assert_eq!(report.online, ["com.acme.online 3.0.0"]);
+
+        let packages = fixture.project_data().join("Packages");
+        assert!(packages.join("com.acme.a-1.0.0.tgz").is_file());
+        assert!(packages.join("com.acme.b-2.0.0.tgz").is_file());
+
+        let expected = json!({
+            "com.acme.a": "file:com.acme.a-1.0.0.tgz",
+            "com.acme.b": "file:com.acme.b-2.0.0.tgz",
+            "com.unity.modules.ui": "1.0.0",
+            "com.acme.tools": "https://github.com/acme/tools.git#v1",
+        });
+        assert_eq!(
+            fixture.dependencies(&packages.join("manifest.json")),
+            expected
+        );
+        assert_eq!(
+            fixture.dependencies(&fixture.project_data().join("Assets").join("manifest.json")),
+            expected
+        );
+        assert_eq!(
+            fixture.dependencies(&fixture.build.join("package").join("package.json")),
+            expected
+        );
+    }
+
+    #[test]
+    fn embeds_packages_without_manifest_entries() {
+        let fixture = fixture(json!({ "com.acme.a": "1.0.0", "com.acme.b": "2.0.0" }));
+
+        let report = vendor(
+            &fixture.config,
+            &fixture.build,
+            &fixture.options(&["com.acme.b"], true),
+        )
+        .unwrap();
+        assert_eq!(report.vendored[0].target, "Packages/com.acme.b");
+
+        let packages = fixture.project_data().join("Packages");
+        assert!(packages.join("com.acme.b").join("package.json").is_file());
+        assert_eq!(
+            fixture.dependencies(&packages.join("manifest.json")),
+            json!({ "com.acme.a": "1.0.0" })
+        );
+    }
+
+    #[test]
+    fn missing_package_changes_nothing() {
+        let fixture = fixture(json!({ "com.acme.a": "1.0.0", "com.acme.missing": "1.0.0" }));
+        let manifest_path = fixture
+            .project_data()
+            .join("Packages")
+            .join("manifest.json");
+        let manifest = fs::read_to_string(&manifest_path).unwrap();
+
+        let Err(error) = vendor(
+            &fixture.config,
+            &fixture.build,
+            &fixture.options(&[], false),
+        ) else {
+            panic!("vendored a package that doesn't exist");
+        };
+        assert!(
+            error.starts_with("could not find com.acme.missing 1.0.0 in"),
+            "{}",
+            error
+        );
+
+        assert_eq!(fs::read_to_string(&manifest_path).unwrap(), manifest);
+        assert!(!fixture
+            .project_data()
+            .join("Assets")
+            .join("manifest.json")
+            .exists());
+        assert!(!fixture
+            .project_data()
+            .join("Packages")
+            .join("com.acme.a-1.0.0.tgz")
+            .exists());
+    }
+}